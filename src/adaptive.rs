@@ -0,0 +1,178 @@
+use crate::camera::{render_at, Camera};
+use crate::canvas::Canvas;
+use crate::world::{color_at, World};
+use crate::Color;
+
+// Anti-aliasing that only spends extra samples where the image changes. Every pixel first
+// gets one sample through its center. Pixels differing from a neighbour by more than the
+// threshold, in any channel, are then split into quadrants, and quadrants that still differ
+// from each other are split again, down to max_depth.
+pub struct AdaptiveSettings {
+    pub threshold: f32,
+    pub max_depth: u32,
+}
+
+impl AdaptiveSettings {
+    pub fn new(threshold: f32, max_depth: u32) -> AdaptiveSettings {
+        AdaptiveSettings { threshold, max_depth }
+    }
+}
+
+pub struct AdaptiveRender {
+    pub canvas: Canvas,
+    // The number of samples averaged into each pixel, row by row.
+    pub samples: Vec<u32>,
+}
+
+impl AdaptiveRender {
+    pub fn samples_at(&self, x: usize, y: usize) -> u32 {
+        self.samples[y * self.canvas.width + x]
+    }
+
+    // Debug image of where the samples went, from black for a single sample to white for
+    // the pixels with the most samples.
+    pub fn sample_count_canvas(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.canvas.width, self.canvas.height);
+        let most = self.samples.iter().copied().max().unwrap_or(1);
+        if most <= 1 {
+            return canvas;
+        }
+        for y in 0..canvas.height {
+            for x in 0..canvas.width {
+                let value = (self.samples_at(x, y) - 1) as f32 / (most - 1) as f32;
+                canvas.set_pixel(x, y, Color::new(value, value, value));
+            }
+        }
+        canvas
+    }
+}
+
+pub fn render_adaptive(camera: &Camera, world: &World, settings: &AdaptiveSettings) -> AdaptiveRender {
+    let mut first_pass = Canvas::new(camera.hsize, camera.vsize);
+    for y in 0..camera.vsize {
+        for x in 0..camera.hsize {
+            first_pass.set_pixel(x, y, render_at(x, y, camera, world));
+        }
+    }
+
+    let mut canvas = Canvas::new(camera.hsize, camera.vsize);
+    let mut samples = vec![1; camera.hsize * camera.vsize];
+    for y in 0..camera.vsize {
+        for x in 0..camera.hsize {
+            if settings.max_depth > 0 && differs_from_neighbours(&first_pass, x, y, settings.threshold) {
+                let mut count = 0;
+                let color = sample_region(camera, world, settings, (x, y), (0.0, 0.0, 1.0), 1, &mut count);
+                canvas.set_pixel(x, y, color);
+                // The first sample isn't part of the refined color.
+                samples[y * camera.hsize + x] = count;
+            } else {
+                canvas.set_pixel(x, y, first_pass.pixel_at(x, y));
+            }
+        }
+    }
+    AdaptiveRender { canvas, samples }
+}
+
+fn differs_from_neighbours(canvas: &Canvas, x: usize, y: usize, threshold: f32) -> bool {
+    let color = canvas.pixel_at(x, y);
+    let neighbours = [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ];
+    neighbours
+        .iter()
+        .filter(|(nx, ny)| *nx < canvas.width && *ny < canvas.height)
+        .any(|(nx, ny)| contrast(&color, &canvas.pixel_at(*nx, *ny)) > threshold)
+}
+
+fn contrast(a: &Color, b: &Color) -> f32 {
+    let difference = *a - *b;
+    difference.red.abs().max(difference.green.abs()).max(difference.blue.abs())
+}
+
+// Averages one sample in the center of each quadrant of the square region (left, top,
+// size) of the pixel (x, y), or recurses into the quadrants if they disagree.
+fn sample_region(
+    camera: &Camera,
+    world: &World,
+    settings: &AdaptiveSettings,
+    pixel: (usize, usize),
+    region: (f32, f32, f32),
+    depth: u32,
+    count: &mut u32,
+) -> Color {
+    let (x, y) = pixel;
+    let (left, top, size) = region;
+    let half = size / 2.0;
+    let quadrants = [(left, top), (left + half, top), (left, top + half), (left + half, top + half)];
+    let mut colors = [Color::new(0.0, 0.0, 0.0); 4];
+    for (color, (quadrant_left, quadrant_top)) in colors.iter_mut().zip(quadrants.iter()) {
+        let ray = camera.ray_for_pixel_offset(x, y, quadrant_left + half / 2.0, quadrant_top + half / 2.0);
+        *color = color_at(world, &ray, 5);
+    }
+    *count += 4;
+
+    let disagree = colors
+        .iter()
+        .any(|a| colors.iter().any(|b| contrast(a, b) > settings.threshold));
+    if disagree && depth < settings.max_depth {
+        for (color, (quadrant_left, quadrant_top)) in colors.iter_mut().zip(quadrants.iter()) {
+            let quadrant = (*quadrant_left, *quadrant_top, half);
+            *color = sample_region(camera, world, settings, pixel, quadrant, depth + 1, count);
+        }
+    }
+
+    (colors[0] + colors[1] + colors[2] + colors[3]) * 0.25
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adaptive::{render_adaptive, AdaptiveSettings};
+    use crate::camera::{render_at, Camera};
+    use crate::color::{BLACK, WHITE};
+    use crate::world::World;
+    use core::f32::consts::PI;
+
+    #[test]
+    fn flat_areas_get_a_single_sample_per_pixel() {
+        let mut world = World::half_covered();
+        let camera = Camera::new(10, 10, PI / 2_f32);
+        world.objects_mut().clear();
+        let render = render_adaptive(&camera, &world, &AdaptiveSettings::new(0.1, 3));
+        assert!(render.samples.iter().all(|count| *count == 1));
+        assert_eq!(render.canvas.pixel_at(3, 3), render_at(3, 3, &camera, &world));
+        assert_eq!(render.sample_count_canvas().pixel_at(3, 3), BLACK);
+    }
+
+    #[test]
+    fn pixels_on_an_edge_are_subdivided() {
+        let world = World::half_covered();
+        let camera = Camera::new(10, 10, PI / 2_f32);
+        let render = render_adaptive(&camera, &world, &AdaptiveSettings::new(0.1, 3));
+        for y in 0..10 {
+            assert_eq!(render.samples_at(0, y), 1);
+            assert_eq!(render.samples_at(9, y), 1);
+            assert_eq!(render.canvas.pixel_at(0, y), WHITE);
+            assert_eq!(render.canvas.pixel_at(9, y), BLACK);
+            // Only the quadrant samples count, not the first one through the center.
+            assert!(render.samples_at(4, y) > 5 && render.samples_at(4, y) % 4 == 0);
+            let edge = render.canvas.pixel_at(4, y);
+            assert!(edge.red > 0.05 && edge.red < 0.95, "Row {}: {:?}", y, edge);
+        }
+
+        let counts = render.sample_count_canvas();
+        assert_eq!(counts.pixel_at(0, 5), BLACK);
+        assert!(counts.pixel_at(4, 5).red > 0.0);
+    }
+
+    #[test]
+    fn a_max_depth_of_zero_turns_subdividing_off() {
+        let world = World::half_covered();
+        let camera = Camera::new(10, 10, PI / 2_f32);
+        let render = render_adaptive(&camera, &world, &AdaptiveSettings::new(0.1, 0));
+        assert!(render.samples.iter().all(|count| *count == 1));
+        assert_eq!(render.canvas.pixel_at(4, 5), render_at(4, 5, &camera, &world));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::canvas::Canvas;
+use crate::math::PI;
+use crate::Color;
+use crate::Tuple;
+
+// What a ray sees when it misses every object, looked up by the direction of the ray.
+pub enum Background {
+    Solid(Color),
+    // Blends from bottom, looking straight down, to top, looking straight up.
+    Gradient { bottom: Color, top: Color },
+    // An equirectangular (latitude/longitude) image covering every direction. The top
+    // row is straight up, and the middle of the image looks towards +z.
+    Image(Canvas),
+}
+
+impl Background {
+    pub fn color_at(&self, direction: &Tuple) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = (direction.normalize().y + 1.0) / 2.0;
+                *bottom * (1.0 - t) + *top * t
+            }
+            Background::Image(canvas) => equirectangular_color_at(canvas, direction),
+        }
+    }
+}
+
+// The pixel of an equirectangular image seen in direction.
+pub fn equirectangular_color_at(canvas: &Canvas, direction: &Tuple) -> Color {
+    let (u, v) = equirectangular_uv(direction);
+    let x = usize::min((u * canvas.width as f32) as usize, canvas.width - 1);
+    let y = usize::min((v * canvas.height as f32) as usize, canvas.height - 1);
+    canvas.pixel_at(x, y)
+}
+
+// Maps a direction to image coordinates from 0 to 1, with v going from up to down.
+pub fn equirectangular_uv(direction: &Tuple) -> (f32, f32) {
+    let direction = direction.normalize();
+    let u = 0.5 + direction.x.atan2(direction.z) / (2.0 * PI);
+    let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::background::{equirectangular_uv, Background};
+    use crate::canvas::Canvas;
+    use crate::color::{BLACK, WHITE};
+    use crate::Color;
+    use crate::Tuple;
+
+    #[test]
+    fn a_solid_background_is_the_same_in_every_direction() {
+        let background = Background::Solid(Color::new(0.2, 0.3, 0.4));
+        assert_eq!(background.color_at(&Tuple::vector(0.0, 1.0, 0.0)), Color::new(0.2, 0.3, 0.4));
+        assert_eq!(background.color_at(&Tuple::vector(1.0, -1.0, 5.0)), Color::new(0.2, 0.3, 0.4));
+    }
+
+    #[test]
+    fn a_gradient_background_blends_from_bottom_to_top() {
+        let background = Background::Gradient { bottom: WHITE, top: Color::new(0.0, 0.0, 1.0) };
+        let test_cases = vec![
+            (Tuple::vector(0.0, -1.0, 0.0), WHITE),
+            (Tuple::vector(0.0, 1.0, 0.0), Color::new(0.0, 0.0, 1.0)),
+            (Tuple::vector(0.0, 0.0, 1.0), Color::new(0.5, 0.5, 1.0)),
+            (Tuple::vector(3.0, 0.0, -4.0), Color::new(0.5, 0.5, 1.0)),
+        ];
+        for test_case in test_cases {
+            assert_eq!(background.color_at(&test_case.0), test_case.1, "Direction: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn mapping_directions_to_an_equirectangular_image() {
+        let test_cases = vec![
+            (Tuple::vector(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Tuple::vector(1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Tuple::vector(-1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Tuple::vector(0.0, 1.0, 0.0), (0.5, 0.0)),
+            (Tuple::vector(0.0, -1.0, 0.0), (0.5, 1.0)),
+        ];
+        for test_case in test_cases {
+            let (u, v) = equirectangular_uv(&test_case.0);
+            assert!((u - (test_case.1).0).abs() < 0.0001, "Direction: {}, u: {}", test_case.0, u);
+            assert!((v - (test_case.1).1).abs() < 0.0001, "Direction: {}, v: {}", test_case.0, v);
+        }
+    }
+
+    #[test]
+    fn an_image_background_is_looked_up_by_direction() {
+        // Top half white, bottom half black, with a red pixel looking towards +x.
+        let mut canvas = Canvas::new(8, 4);
+        for y in 0..2 {
+            for x in 0..8 {
+                canvas.set_pixel(x, y, WHITE);
+            }
+        }
+        canvas.set_pixel(6, 2, Color::new(1.0, 0.0, 0.0));
+        let background = Background::Image(canvas);
+
+        assert_eq!(background.color_at(&Tuple::vector(0.0, 1.0, 0.0)), WHITE);
+        assert_eq!(background.color_at(&Tuple::vector(0.0, -1.0, 0.0)), BLACK);
+        assert_eq!(background.color_at(&Tuple::vector(1.0, -0.1, 0.0)), Color::new(1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::math::INFINITY;
+use crate::Matrix4;
+use crate::Ray;
+use crate::Tuple;
+
+// Axis-aligned bounding box. An empty box has min at +infinity and max at -infinity,
+// so adding the first point or box makes it that point or box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl BoundingBox {
+    pub fn new(min: Tuple, max: Tuple) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    pub fn empty() -> BoundingBox {
+        BoundingBox {
+            min: Tuple::point(INFINITY, INFINITY, INFINITY),
+            max: Tuple::point(-INFINITY, -INFINITY, -INFINITY),
+        }
+    }
+
+    pub fn infinite() -> BoundingBox {
+        BoundingBox {
+            min: Tuple::point(-INFINITY, -INFINITY, -INFINITY),
+            max: Tuple::point(INFINITY, INFINITY, INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min.x.is_finite()
+            && self.min.y.is_finite()
+            && self.min.z.is_finite()
+            && self.max.x.is_finite()
+            && self.max.y.is_finite()
+            && self.max.z.is_finite()
+    }
+
+    pub fn add_point(&mut self, point: &Tuple) {
+        self.min.x = f32::min(self.min.x, point.x);
+        self.min.y = f32::min(self.min.y, point.y);
+        self.min.z = f32::min(self.min.z, point.z);
+        self.max.x = f32::max(self.max.x, point.x);
+        self.max.y = f32::max(self.max.y, point.y);
+        self.max.z = f32::max(self.max.z, point.z);
+    }
+
+    pub fn merge(&mut self, other: &BoundingBox) {
+        if other.is_empty() {
+            return;
+        }
+        self.add_point(&other.min);
+        self.add_point(&other.max);
+    }
+
+    pub fn contains_point(&self, point: &Tuple) -> bool {
+        self.min.x <= point.x
+            && point.x <= self.max.x
+            && self.min.y <= point.y
+            && point.y <= self.max.y
+            && self.min.z <= point.z
+            && point.z <= self.max.z
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+
+    pub fn centroid(&self) -> Tuple {
+        Tuple::point(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let dx = self.max.x - self.min.x;
+        let dy = self.max.y - self.min.y;
+        let dz = self.max.z - self.min.z;
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    // Slab test against the whole line the ray lies on, including negative t. Culling
+    // must not drop intersections behind the ray, as refraction looks at all of them.
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        self.intersects_between(ray, -INFINITY, INFINITY)
+    }
+
+    // Slab test against the part of the ray between t_from and t_to.
+    pub fn intersects_between(&self, ray: &Ray, t_from: f32, t_to: f32) -> bool {
+        let mut tmin = t_from;
+        let mut tmax = t_to;
+        for axis in 0..3 {
+            let origin = ray.origin[axis];
+            let direction = ray.direction[axis];
+            if direction == 0.0 {
+                if origin < self.min[axis] || origin > self.max[axis] {
+                    return false;
+                }
+                continue;
+            }
+            let t1 = (self.min[axis] - origin) / direction;
+            let t2 = (self.max[axis] - origin) / direction;
+            tmin = f32::max(tmin, f32::min(t1, t2));
+            tmax = f32::min(tmax, f32::max(t1, t2));
+        }
+        tmin <= tmax
+    }
+
+    // The box around the transformed corners. Boxes reaching infinity, like the one of a
+    // plane, can't be transformed corner by corner (infinity * 0 is NaN), so they become
+    // infinite in every direction.
+    pub fn transform(&self, transformation: &Matrix4) -> BoundingBox {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_finite() {
+            return BoundingBox::infinite();
+        }
+
+        let corners = [
+            Tuple::point(self.min.x, self.min.y, self.min.z),
+            Tuple::point(self.min.x, self.min.y, self.max.z),
+            Tuple::point(self.min.x, self.max.y, self.min.z),
+            Tuple::point(self.min.x, self.max.y, self.max.z),
+            Tuple::point(self.max.x, self.min.y, self.min.z),
+            Tuple::point(self.max.x, self.min.y, self.max.z),
+            Tuple::point(self.max.x, self.max.y, self.min.z),
+            Tuple::point(self.max.x, self.max.y, self.max.z),
+        ];
+        let mut result = BoundingBox::empty();
+        for corner in corners.iter() {
+            result.add_point(&(transformation * corner));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::BoundingBox;
+    use crate::math::INFINITY;
+    use crate::Matrix4;
+    use crate::Ray;
+    use crate::Tuple;
+    use core::f32::consts::{FRAC_1_SQRT_2, PI, SQRT_2};
+
+    #[test]
+    fn adding_points_to_an_empty_bounding_box() {
+        let mut bounds = BoundingBox::empty();
+        assert!(bounds.is_empty());
+        bounds.add_point(&Tuple::point(-5.0, 2.0, 0.0));
+        bounds.add_point(&Tuple::point(7.0, 0.0, -3.0));
+        assert!(!bounds.is_empty());
+        assert_eq!(bounds.min, Tuple::point(-5.0, 0.0, -3.0));
+        assert_eq!(bounds.max, Tuple::point(7.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn merging_two_bounding_boxes() {
+        let mut bounds = BoundingBox::new(Tuple::point(-5.0, -2.0, 0.0), Tuple::point(7.0, 4.0, 4.0));
+        bounds.merge(&BoundingBox::new(Tuple::point(8.0, -7.0, -2.0), Tuple::point(14.0, 2.0, 8.0)));
+        bounds.merge(&BoundingBox::empty());
+        assert_eq!(bounds.min, Tuple::point(-5.0, -7.0, -2.0));
+        assert_eq!(bounds.max, Tuple::point(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn checking_if_a_box_contains_a_point_or_a_box() {
+        let bounds = BoundingBox::new(Tuple::point(5.0, -2.0, 0.0), Tuple::point(11.0, 4.0, 7.0));
+        assert!(bounds.contains_point(&Tuple::point(5.0, -2.0, 0.0)));
+        assert!(bounds.contains_point(&Tuple::point(8.0, 1.0, 3.0)));
+        assert!(!bounds.contains_point(&Tuple::point(3.0, 0.0, 3.0)));
+        assert!(!bounds.contains_point(&Tuple::point(8.0, 1.0, 8.0)));
+        assert!(bounds.contains_box(&BoundingBox::new(Tuple::point(6.0, -1.0, 1.0), Tuple::point(10.0, 3.0, 6.0))));
+        assert!(!bounds.contains_box(&BoundingBox::new(Tuple::point(4.0, -3.0, -1.0), Tuple::point(10.0, 3.0, 6.0))));
+    }
+
+    #[test]
+    fn transforming_a_bounding_box() {
+        let bounds = BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0));
+        let transformation = Matrix4::identity().rotate_x(PI / 4.0).rotate_y(PI / 4.0);
+        let result = bounds.transform(&transformation);
+        let corner = 1.0 + FRAC_1_SQRT_2;
+        assert_eq!(result.min, Tuple::point(-SQRT_2, -corner, -corner));
+        assert_eq!(result.max, Tuple::point(SQRT_2, corner, corner));
+    }
+
+    #[test]
+    fn transforming_an_infinite_bounding_box() {
+        let bounds = BoundingBox::new(Tuple::point(-INFINITY, 0.0, -INFINITY), Tuple::point(INFINITY, 0.0, INFINITY));
+        let result = bounds.transform(&Matrix4::identity().rotate_x(PI / 2.0));
+        assert_eq!(result, BoundingBox::infinite());
+        assert!(BoundingBox::empty().transform(&Matrix4::identity().scale(2.0, 2.0, 2.0)).is_empty());
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_bounding_box() {
+        let bounds = BoundingBox::new(Tuple::point(5.0, -2.0, 0.0), Tuple::point(11.0, 4.0, 7.0));
+        let test_cases = vec![
+            (Tuple::point(15.0, 1.0, 2.0), Tuple::vector(-1.0, 0.0, 0.0), true),
+            (Tuple::point(-5.0, -1.0, 4.0), Tuple::vector(1.0, 0.0, 0.0), true),
+            (Tuple::point(7.0, 6.0, 5.0), Tuple::vector(0.0, -1.0, 0.0), true),
+            (Tuple::point(9.0, -5.0, 6.0), Tuple::vector(0.0, 1.0, 0.0), true),
+            (Tuple::point(8.0, 2.0, 12.0), Tuple::vector(0.0, 0.0, -1.0), true),
+            (Tuple::point(6.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), true),
+            (Tuple::point(8.0, 1.0, 3.5), Tuple::vector(0.0, 0.0, 1.0), true),
+            (Tuple::point(9.0, -1.0, -8.0), Tuple::vector(2.0, 4.0, 6.0), false),
+            (Tuple::point(8.0, 3.0, -4.0), Tuple::vector(6.0, 2.0, 4.0), false),
+            (Tuple::point(9.0, -1.0, -2.0), Tuple::vector(4.0, 6.0, 2.0), false),
+            (Tuple::point(4.0, 0.0, 9.0), Tuple::vector(0.0, 0.0, -1.0), false),
+            (Tuple::point(8.0, 6.0, -1.0), Tuple::vector(0.0, -1.0, 0.0), false),
+            (Tuple::point(12.0, 5.0, 4.0), Tuple::vector(-1.0, 0.0, 0.0), false),
+            // Boxes behind the ray still count, since we test the whole line.
+            (Tuple::point(8.0, 1.0, 20.0), Tuple::vector(0.0, 0.0, 1.0), true),
+        ];
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1.normalize());
+            assert_eq!(bounds.intersects(&ray), test_case.2, "Origin: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn intersecting_a_part_of_a_ray_with_a_bounding_box() {
+        let bounds = BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0));
+        let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(bounds.intersects_between(&ray, 0.0, 10.0));
+        assert!(bounds.intersects_between(&ray, 5.0, 5.5));
+        assert!(!bounds.intersects_between(&ray, 0.0, 3.5));
+        assert!(!bounds.intersects_between(&ray, 7.0, 10.0));
+    }
+}
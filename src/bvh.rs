@@ -0,0 +1,398 @@
+use crate::bounds::BoundingBox;
+use crate::ray::Intersection;
+use crate::Ray;
+use crate::Shape;
+
+const BIN_COUNT: usize = 12;
+const MAX_SHAPES_IN_LEAF: usize = 4;
+// Cost of visiting a node, relative to intersecting a shape.
+const TRAVERSAL_COST: f32 = 0.125;
+
+enum BvhNode {
+    Leaf { bounds: BoundingBox, first: usize, count: usize },
+    Interior { bounds: BoundingBox, left: usize, right: usize },
+}
+
+struct BuildEntry {
+    index: usize,
+    bounds: BoundingBox,
+    centroid: [f32; 3],
+}
+
+// Bounding volume hierarchy over a list of shapes, built with the surface area heuristic.
+// It only stores indices, so it must be rebuilt if the list of shapes changes. Shapes with
+// infinite bounds, like planes, can't be put in a box and are always tested.
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+    unbounded: Vec<usize>,
+    shape_count: usize,
+}
+
+impl Bvh {
+    pub fn build(shapes: &[Box<dyn Shape>]) -> Bvh {
+        let mut entries = vec![];
+        let mut unbounded = vec![];
+        for (index, shape) in shapes.iter().enumerate() {
+            let bounds = shape.parent_space_bounds();
+            if bounds.is_empty() {
+                continue;
+            }
+            if !bounds.is_finite() {
+                unbounded.push(index);
+                continue;
+            }
+            let centroid = bounds.centroid();
+            entries.push(BuildEntry {
+                index,
+                bounds,
+                centroid: [centroid.x, centroid.y, centroid.z],
+            });
+        }
+
+        let mut bvh = Bvh {
+            nodes: vec![],
+            indices: vec![],
+            unbounded,
+            shape_count: shapes.len(),
+        };
+        if !entries.is_empty() {
+            bvh.build_node(&mut entries);
+        }
+        bvh
+    }
+
+    // The intersections are not sorted, like Shape::intersections_by. The nodes are tested
+    // with node_ray, which must be in the space the shapes' parent space bounds are in,
+    // while the shapes themselves are intersected with ray.
+    pub fn intersect<'a>(
+        &self,
+        shapes: &'a [Box<dyn Shape>],
+        node_ray: &Ray,
+        ray: &Ray,
+    ) -> Vec<Intersection<'a>> {
+        debug_assert_eq!(self.shape_count, shapes.len(), "The BVH is out of date");
+        let mut xs = vec![];
+        for &index in self.unbounded.iter() {
+            xs.extend(shapes[index].intersections_by(ray));
+        }
+        if self.nodes.is_empty() {
+            return xs;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            match &self.nodes[node_index] {
+                BvhNode::Leaf { bounds, first, count } => {
+                    if bounds.intersects(node_ray) {
+                        for &index in self.indices[*first..*first + *count].iter() {
+                            xs.extend(shapes[index].intersections_by(ray));
+                        }
+                    }
+                }
+                BvhNode::Interior { bounds, left, right } => {
+                    if bounds.intersects(node_ray) {
+                        stack.push(*right);
+                        stack.push(*left);
+                    }
+                }
+            }
+        }
+        xs
+    }
+
+    // Like intersect, but stops at the first shape occluding the ray within max_distance,
+    // and skips the nodes that lie outside of it.
+    pub fn occludes(&self, shapes: &[Box<dyn Shape>], node_ray: &Ray, ray: &Ray, max_distance: f32) -> bool {
+        debug_assert_eq!(self.shape_count, shapes.len(), "The BVH is out of date");
+        if self.unbounded.iter().any(|&index| shapes[index].occludes(ray, max_distance)) {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            match &self.nodes[node_index] {
+                BvhNode::Leaf { bounds, first, count } => {
+                    if bounds.intersects_between(node_ray, 0.0, max_distance)
+                        && self.indices[*first..*first + *count]
+                            .iter()
+                            .any(|&index| shapes[index].occludes(ray, max_distance))
+                    {
+                        return true;
+                    }
+                }
+                BvhNode::Interior { bounds, left, right } => {
+                    if bounds.intersects_between(node_ray, 0.0, max_distance) {
+                        stack.push(*right);
+                        stack.push(*left);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    pub fn depth(&self) -> usize {
+        if self.nodes.is_empty() {
+            return 0;
+        }
+        self.node_depth(0)
+    }
+
+    fn node_depth(&self, node_index: usize) -> usize {
+        match &self.nodes[node_index] {
+            BvhNode::Leaf { .. } => 1,
+            BvhNode::Interior { left, right, .. } => {
+                1 + usize::max(self.node_depth(*left), self.node_depth(*right))
+            }
+        }
+    }
+
+    fn push_leaf(&mut self, bounds: BoundingBox, entries: &[BuildEntry]) -> usize {
+        let first = self.indices.len();
+        self.indices.extend(entries.iter().map(|e| e.index));
+        self.nodes.push(BvhNode::Leaf {
+            bounds,
+            first,
+            count: entries.len(),
+        });
+        self.nodes.len() - 1
+    }
+
+    fn build_node(&mut self, entries: &mut [BuildEntry]) -> usize {
+        let mut bounds = BoundingBox::empty();
+        let mut centroid_min = [f32::MAX; 3];
+        let mut centroid_max = [f32::MIN; 3];
+        for entry in entries.iter() {
+            bounds.merge(&entry.bounds);
+            for axis in 0..3 {
+                centroid_min[axis] = f32::min(centroid_min[axis], entry.centroid[axis]);
+                centroid_max[axis] = f32::max(centroid_max[axis], entry.centroid[axis]);
+            }
+        }
+
+        if entries.len() <= 2 {
+            return self.push_leaf(bounds, entries);
+        }
+
+        // Sort the shapes into bins by their centroid, and find the split between two
+        // bins that gives the lowest cost: each side's area times its number of shapes.
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..3 {
+            let extent = centroid_max[axis] - centroid_min[axis];
+            if extent <= 0.0 {
+                continue;
+            }
+            let mut bin_counts = [0_usize; BIN_COUNT];
+            let mut bin_bounds = [BoundingBox::empty(); BIN_COUNT];
+            for entry in entries.iter() {
+                let bin = bin_index(entry.centroid[axis], centroid_min[axis], extent);
+                bin_counts[bin] += 1;
+                bin_bounds[bin].merge(&entry.bounds);
+            }
+
+            for split in 1..BIN_COUNT {
+                let mut left_bounds = BoundingBox::empty();
+                let mut left_count = 0;
+                for bin in 0..split {
+                    left_bounds.merge(&bin_bounds[bin]);
+                    left_count += bin_counts[bin];
+                }
+                let mut right_bounds = BoundingBox::empty();
+                let mut right_count = 0;
+                for bin in split..BIN_COUNT {
+                    right_bounds.merge(&bin_bounds[bin]);
+                    right_count += bin_counts[bin];
+                }
+                if left_count == 0 || right_count == 0 {
+                    continue;
+                }
+                let cost = left_bounds.surface_area() * left_count as f32
+                    + right_bounds.surface_area() * right_count as f32;
+                if best.is_none() || cost < best.unwrap().0 {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        let (cost, axis, split) = match best {
+            Some(b) => b,
+            // All centroids are in the same place, so there is nothing to split on.
+            None => return self.push_leaf(bounds, entries),
+        };
+
+        let area = bounds.surface_area();
+        let split_cost = if area > 0.0 { TRAVERSAL_COST + cost / area } else { TRAVERSAL_COST };
+        if split_cost >= entries.len() as f32 && entries.len() <= MAX_SHAPES_IN_LEAF {
+            return self.push_leaf(bounds, entries);
+        }
+
+        let extent = centroid_max[axis] - centroid_min[axis];
+        let mut middle = 0;
+        for i in 0..entries.len() {
+            if bin_index(entries[i].centroid[axis], centroid_min[axis], extent) < split {
+                entries.swap(i, middle);
+                middle += 1;
+            }
+        }
+
+        // Reserve the slot for this node before the children, so the root is always at 0.
+        let node_index = self.nodes.len();
+        self.nodes.push(BvhNode::Leaf { bounds, first: 0, count: 0 });
+        let (left_entries, right_entries) = entries.split_at_mut(middle);
+        let left = self.build_node(left_entries);
+        let right = self.build_node(right_entries);
+        self.nodes[node_index] = BvhNode::Interior { bounds, left, right };
+        node_index
+    }
+}
+
+fn bin_index(centroid: f32, min: f32, extent: f32) -> usize {
+    let bin = ((centroid - min) / extent * BIN_COUNT as f32) as usize;
+    usize::min(bin, BIN_COUNT - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bvh::Bvh;
+    use crate::camera::{render_at, Camera};
+    use crate::group::Group;
+    use crate::lighting::PointLight;
+    use crate::math::PI;
+    use crate::plane::Plane;
+    use crate::shape::Shape;
+    use crate::sphere::Sphere;
+    use crate::transformation::view_transform;
+    use crate::triangle::Triangle;
+    use crate::world::{intersect_world, World};
+    use crate::Color;
+    use crate::Matrix4;
+    use crate::Ray;
+    use crate::Tuple;
+
+    fn sphere_grid(first_id: u32, size: u32) -> Vec<Box<dyn Shape>> {
+        let mut shapes: Vec<Box<dyn Shape>> = vec![];
+        for i in 0..size * size {
+            let mut sphere = Sphere::new(first_id + i);
+            let x = (i % size) as f32 * 3.0;
+            let z = (i / size) as f32 * 3.0;
+            sphere.set_transformation(Matrix4::identity().translate(x, 1.0, z));
+            shapes.push(Box::new(sphere));
+        }
+        shapes
+    }
+
+    #[test]
+    fn a_bvh_over_many_shapes_is_split_into_a_tree() {
+        let shapes = sphere_grid(1, 8);
+        let bvh = Bvh::build(&shapes);
+        assert!(bvh.depth() > 3);
+        assert_eq!(bvh.indices.len(), 64);
+        assert_eq!(bvh.unbounded.len(), 0);
+    }
+
+    #[test]
+    fn shapes_with_infinite_bounds_are_always_tested() {
+        let mut shapes = sphere_grid(1, 2);
+        shapes.push(Box::new(Plane::new(5)));
+        let bvh = Bvh::build(&shapes);
+        assert_eq!(bvh.unbounded, vec![4]);
+
+        let ray = Ray::new(Tuple::point(100.0, 5.0, 100.0), Tuple::vector(0.0, -1.0, 0.0));
+        let xs = bvh.intersect(&shapes, &ray, &ray);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].obj.get_id(), 5);
+    }
+
+    #[test]
+    fn a_bvh_finds_the_same_intersections_as_testing_every_shape() {
+        let shapes = sphere_grid(1, 8);
+        let bvh = Bvh::build(&shapes);
+        let ray = Ray::new(Tuple::point(-5.0, 1.0, -5.0), Tuple::vector(1.0, 0.0, 1.0).normalize());
+
+        let mut expected: Vec<_> = shapes.iter().flat_map(|s| s.intersections_by(&ray)).collect();
+        let mut xs = bvh.intersect(&shapes, &ray, &ray);
+        expected.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        assert_eq!(xs.len(), 16);
+        assert_eq!(xs.len(), expected.len());
+        for (a, b) in xs.iter().zip(expected.iter()) {
+            assert!(a == b);
+        }
+    }
+
+    #[test]
+    fn a_bvh_finds_occluders_only_within_the_distance() {
+        let shapes = sphere_grid(1, 8);
+        let bvh = Bvh::build(&shapes);
+        let ray = Ray::new(Tuple::point(-5.0, 1.0, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+        assert!(bvh.occludes(&shapes, &ray, &ray, 100.0));
+        assert!(bvh.occludes(&shapes, &ray, &ray, 4.5));
+        assert!(!bvh.occludes(&shapes, &ray, &ray, 3.5));
+
+        let ray = Ray::new(Tuple::point(30.0, 1.0, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+        assert!(!bvh.occludes(&shapes, &ray, &ray, 100.0));
+    }
+
+    fn test_world() -> World {
+        let light = PointLight::new(Tuple::point(-10.0, 20.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let mut world = World::new(light);
+        world.add_object(Plane::new(1));
+
+        let mut spheres = Group::new(2);
+        for sphere in sphere_grid(100, 6) {
+            spheres.add_child(sphere);
+        }
+        spheres.set_transformation(Matrix4::identity().translate(-8.0, 0.0, 0.0).scale(0.5, 0.5, 0.5));
+        world.add_object(spheres);
+
+        for i in 0..20 {
+            let x = i as f32 * 0.5 - 5.0;
+            let mut triangle = Triangle::new(
+                200 + i,
+                Tuple::point(x, 0.5, 3.0),
+                Tuple::point(x + 0.4, 0.5, 3.0),
+                Tuple::point(x + 0.2, 2.0, 3.5),
+            );
+            triangle.material.color = Color::new(0.2, 0.8, 0.2);
+            world.add_object(triangle);
+        }
+        for mut sphere in sphere_grid(300, 3) {
+            sphere.get_mut_material().reflective = 0.5;
+            sphere.get_mut_material().transparency = 0.5;
+            sphere.get_mut_material().refractive_index = 1.5;
+            world.objects_mut().push(sphere);
+        }
+        world
+    }
+
+    #[test]
+    fn rendering_with_and_without_a_bvh_gives_identical_images() {
+        let without_bvh = test_world();
+        let mut with_bvh = test_world();
+        with_bvh.build_bvh();
+
+        let mut camera = Camera::new(40, 30, PI / 3.0);
+        let from = Tuple::point(0.0, 6.0, -12.0);
+        let to = Tuple::point(0.0, 1.0, 3.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        camera.set_transform(&view_transform(&from, &to, &up));
+
+        for y in 0..camera.vsize {
+            for x in 0..camera.hsize {
+                let expected = render_at(x, y, &camera, &without_bvh);
+                let color = render_at(x, y, &camera, &with_bvh);
+                assert_eq!(color, expected, "Pixel: {}, {}", x, y);
+            }
+        }
+
+        let ray = camera.ray_for_pixel(20, 15);
+        assert_eq!(
+            intersect_world(&with_bvh, &ray).len(),
+            intersect_world(&without_bvh, &ray).len()
+        );
+    }
+}
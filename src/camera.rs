@@ -1,13 +1,49 @@
+use crate::math::PI;
 use crate::matrix::inverse4;
+use crate::random::Rng;
+use crate::sampling::{sample_disk, sample_polygon, Sampler};
 use crate::world::{color_at, World};
 use crate::Color;
 use crate::Matrix4;
 use crate::Ray;
 use crate::Tuple;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // Rays spread out from the camera, through a canvas 1 unit in front of it.
+    Perspective,
+    // Parallel rays along the view direction, starting on the canvas, which is centered
+    // on the camera. Sizes don't change with the distance.
+    Orthographic,
+    // Every direction, with longitude across and latitude down the image. The middle of the
+    // image looks forward. Turned to look towards +z, the image matches the layout
+    // Background::Image and EnvironmentLight expect.
+    Equirectangular,
+    // Equidistant fisheye, where the angle from the view direction grows linearly with the
+    // distance from the middle of the image, reaching field_of_view / 2 at the edge of the
+    // circle fitting the image. Beyond it the mapping carries on, up to looking straight
+    // back, so the corners aren't left empty.
+    Fisheye { field_of_view: f32 },
+}
+
 pub struct Camera {
     pub hsize: usize,
     pub vsize: usize,    
+    pub projection: Projection,
+    // Radius of the lens. Zero gives a pinhole camera, with everything in focus.
+    pub aperture_radius: f32,
+    // Distance to the plane in focus, along the view direction.
+    pub focal_distance: f32,
+    // Number of blades in the aperture, giving polygonal bokeh. Zero for a round aperture.
+    pub aperture_blades: u32,
+    // The rays get times spread over the interval the shutter is open, blurring moving
+    // shapes. The same time everywhere when they are equal.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+    // Moves the canvas within its plane, without turning the camera, for off-axis
+    // projections. In canvas units, where +x is towards the left of the image.
+    pub shift_x: f32,
+    pub shift_y: f32,
     inverse_transform: Matrix4,
     half_height: f32,
     half_width: f32,
@@ -34,6 +70,14 @@ impl Camera {
         Camera {
             hsize,
             vsize,            
+            projection: Projection::Perspective,
+            aperture_radius: 0_f32,
+            focal_distance: 1_f32,
+            aperture_blades: 0,
+            shutter_open: 0_f32,
+            shutter_close: 0_f32,
+            shift_x: 0_f32,
+            shift_y: 0_f32,
             inverse_transform: Matrix4::identity(),
             half_height,
             half_width,
@@ -41,21 +85,127 @@ impl Camera {
         }
     }
 
+    // An orthographic camera, seeing width units of the world across the image.
+    pub fn new_orthographic(hsize: usize, vsize: usize, width: f32) -> Camera {
+        let mut camera = Camera::new(hsize, vsize, PI / 2_f32);
+        camera.projection = Projection::Orthographic;
+        camera.half_width = width / 2_f32;
+        camera.half_height = camera.half_width * vsize as f32 / hsize as f32;
+        camera.pixel_size = width / hsize as f32;
+        camera
+    }
+
+    // A 360 degree camera. Panoramic cameras have no lens, and ignore the aperture.
+    pub fn new_equirectangular(hsize: usize, vsize: usize) -> Camera {
+        let mut camera = Camera::new(hsize, vsize, PI / 2_f32);
+        camera.projection = Projection::Equirectangular;
+        camera
+    }
+
+    pub fn new_fisheye(hsize: usize, vsize: usize, field_of_view: f32) -> Camera {
+        let mut camera = Camera::new(hsize, vsize, PI / 2_f32);
+        camera.projection = Projection::Fisheye { field_of_view };
+        camera
+    }
+
     pub fn set_transform(&mut self, transform: &Matrix4) {
         self.inverse_transform = inverse4(transform);
     }
 
     pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        let x_offset = (x as f32 + 0.5_f32) * self.pixel_size;
-        let y_offset = (y as f32 + 0.5_f32) * self.pixel_size;
+        self.ray_for_pixel_offset(x, y, 0.5_f32, 0.5_f32)
+    }
+
+    // Ray through a point of the pixel, where (0, 0) is its top left corner and (1, 1) the
+    // bottom right. Offsets outside that range end up in the neighbouring pixels.
+    pub fn ray_for_pixel_offset(&self, x: usize, y: usize, dx: f32, dy: f32) -> Ray {
+        let shutter_time = self.shutter_close - self.shutter_open;
+        if self.aperture_radius <= 0_f32 && shutter_time <= 0_f32 {
+            return self.ray_for_pixel_lens(x, y, dx, dy, 0.5_f32, 0.5_f32);
+        }
+        // Seeded by the sample, so that supersampling spreads the rays over the lens and
+        // the shutter interval while renders stay the same from run to run.
+        let mut rng = Rng::from_floats(&[x as f32, y as f32, dx, dy]);
+        let mut ray = self.ray_for_pixel_lens(x, y, dx, dy, rng.next_f32(), rng.next_f32());
+        if shutter_time > 0_f32 {
+            ray.time = self.shutter_open + rng.next_f32() * shutter_time;
+        }
+        ray
+    }
+
+    // Ray from the point (lens_u, lens_v), both in [0, 1), on the lens through the point of
+    // the focal plane seen through the pixel offset.
+    pub fn ray_for_pixel_lens(&self, x: usize, y: usize, dx: f32, dy: f32, lens_u: f32, lens_v: f32) -> Ray {
+        let x_offset = (x as f32 + dx) * self.pixel_size;
+        let y_offset = (y as f32 + dy) * self.pixel_size;
+
+        let world_x = self.half_width - x_offset + self.shift_x;
+        let world_y = self.half_height - y_offset + self.shift_y;
+
+        // Without a lens every ray goes through the center of it, and the focal distance
+        // doesn't matter.
+        let (lens_x, lens_y, focal_distance) = if self.aperture_radius <= 0_f32 {
+            (0_f32, 0_f32, 1_f32)
+        } else {
+            let (lens_x, lens_y) = if self.aperture_blades >= 3 {
+                sample_polygon(self.aperture_blades, lens_u, lens_v)
+            } else {
+                sample_disk(lens_u, lens_v)
+            };
+            (lens_x * self.aperture_radius, lens_y * self.aperture_radius, self.focal_distance)
+        };
+
+        let (origin, focus) = match self.projection {
+            // The canvas is 1 unit from the camera, so scaling it gives the focal plane.
+            Projection::Perspective => (
+                Tuple::point(lens_x, lens_y, 0_f32),
+                Tuple::point(world_x * focal_distance, world_y * focal_distance, -focal_distance),
+            ),
+            Projection::Orthographic => (
+                Tuple::point(world_x + lens_x, world_y + lens_y, 0_f32),
+                Tuple::point(world_x, world_y, -focal_distance),
+            ),
+            Projection::Equirectangular => (
+                Tuple::point(0_f32, 0_f32, 0_f32),
+                Tuple::point(0_f32, 0_f32, 0_f32) + self.equirectangular_direction(x as f32 + dx, y as f32 + dy),
+            ),
+            Projection::Fisheye { field_of_view } => (
+                Tuple::point(0_f32, 0_f32, 0_f32),
+                Tuple::point(0_f32, 0_f32, 0_f32) + self.fisheye_direction(x as f32 + dx, y as f32 + dy, field_of_view),
+            ),
+        };
+        let origin = &self.inverse_transform * &origin;
+        let focus = &self.inverse_transform * &focus;
+        let direction = (focus - origin).normalize();
+        Ray::new_at_time(origin, direction, self.shutter_open)
+    }
 
-        let world_x = self.half_width - x_offset;
-        let world_y = self.half_height - y_offset;
+    // Camera space direction through the point (x, y) of the image, in pixels.
+    fn equirectangular_direction(&self, x: f32, y: f32) -> Tuple {
+        let longitude = (0.5_f32 - x / self.hsize as f32) * 2_f32 * PI;
+        let polar = y / self.vsize as f32 * PI;
+        Tuple::vector(
+            polar.sin() * longitude.sin(),
+            polar.cos(),
+            -polar.sin() * longitude.cos(),
+        )
+    }
 
-        let pixel = &self.inverse_transform * &Tuple::point(world_x, world_y, -1_f32);
-        let origin = &self.inverse_transform * &Tuple::point(0_f32, 0_f32, 0_f32);
-        let direction = (pixel - origin).normalize();
-        Ray { origin, direction }
+    fn fisheye_direction(&self, x: f32, y: f32, field_of_view: f32) -> Tuple {
+        // Left in the image is +x, like for the canvas.
+        let px = self.hsize as f32 / 2_f32 - x;
+        let py = self.vsize as f32 / 2_f32 - y;
+        let distance = (px * px + py * py).sqrt();
+        if distance == 0_f32 {
+            return Tuple::vector(0_f32, 0_f32, -1_f32);
+        }
+        let radius = usize::min(self.hsize, self.vsize) as f32 / 2_f32;
+        let angle = f32::min(distance / radius * field_of_view / 2_f32, PI);
+        Tuple::vector(
+            angle.sin() * px / distance,
+            angle.sin() * py / distance,
+            -angle.cos(),
+        )
     }
 }
 
@@ -83,9 +233,44 @@ pub fn render_at(x: usize, y: usize, camera: &Camera, world: &World) -> Color {
     return color_at(world, &ray, 5);
 }
 
+// Anti-aliased color of a pixel: the filter weighted average of all the samples. The
+// samples are spread in proportion to the magnitude of the filter, so the samples under
+// its positive and its negative parts are averaged separately, and then weighted by the
+// exact areas of those parts. Normalizing by the sum of the signed weights of the samples
+// instead would blow up whenever they nearly cancel out.
+pub fn render_pixel(x: usize, y: usize, camera: &Camera, world: &World, sampler: &Sampler) -> Color {
+    // Seeded by the pixel, so the jitter is the same whichever thread renders it.
+    let mut rng = Rng::new((y * camera.hsize + x) as u64);
+    let mut positive = (Color::new(0_f32, 0_f32, 0_f32), 0);
+    let mut negative = (Color::new(0_f32, 0_f32, 0_f32), 0);
+    for (dx, dy) in sampler.offsets(&mut rng) {
+        let ray = camera.ray_for_pixel_offset(x, y, 0.5_f32 + dx, 0.5_f32 + dy);
+        let sum = if sampler.filter.weight(dx, dy) < 0_f32 { &mut negative } else { &mut positive };
+        sum.0 = sum.0 + color_at(world, &ray, 5);
+        sum.1 += 1;
+    }
+    // Without samples on one side, the other side's average is the best guess for it.
+    let average = |(sum, count): (Color, usize), (other_sum, other_count): (Color, usize)| {
+        if count > 0 {
+            sum * (1_f32 / count as f32)
+        } else {
+            other_sum * (1_f32 / other_count as f32)
+        }
+    };
+    let (positive_area, negative_area) = sampler.filter.lobe_areas();
+    let total_area = positive_area - negative_area;
+    average(positive, negative) * (positive_area / total_area)
+        - average(negative, positive) * (negative_area / total_area)
+}
+
 #[cfg(test)]
 mod camera_tests {
-    use crate::camera::{Camera};
+    use crate::background::equirectangular_uv;
+    use crate::camera::{render_at, render_pixel, Camera, Projection};
+    use crate::color::{BLACK, WHITE};
+    use crate::sampling::{Filter, SamplePattern, Sampler};
+    use crate::transformation::view_transform;
+    use crate::world::World;
     use crate::Matrix4;        
     use crate::Tuple;
     use core::f32::consts::PI;
@@ -136,6 +321,235 @@ mod camera_tests {
         );
     }
 
+    #[test]
+    fn an_orthographic_camera_casts_parallel_rays() {
+        let camera = Camera::new_orthographic(200, 100, 10.0);
+        assert_eq!(camera.projection, Projection::Orthographic);
+        assert_eq!(camera.pixel_size, 0.05);
+        let test_cases = vec![
+            (100, 50, Tuple::point(-0.025, -0.025, 0.0)),
+            (0, 0, Tuple::point(4.975, 2.475, 0.0)),
+            (199, 99, Tuple::point(-4.975, -2.475, 0.0)),
+        ];
+        for (x, y, origin) in test_cases {
+            let ray = camera.ray_for_pixel(x, y);
+            assert_eq!(ray.origin, origin);
+            assert_eq!(ray.direction, Tuple::vector(0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn a_transformed_orthographic_camera() {
+        let mut camera = Camera::new_orthographic(201, 101, 20.1);
+        let from = Tuple::point(0.0, 0.0, 8.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        camera.set_transform(&view_transform(&from, &to, &up));
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin, Tuple::point(0.0, 0.0, 8.0));
+        assert_eq!(ray.direction, Tuple::vector(0.0, 0.0, -1.0));
+        let ray = camera.ray_for_pixel(0, 50);
+        assert_eq!(ray.origin, Tuple::point(10.0, 0.0, 8.0));
+        assert_eq!(ray.direction, Tuple::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn orthographic_rays_through_the_lens_converge_on_the_focal_plane() {
+        let mut camera = Camera::new_orthographic(201, 101, 20.1);
+        camera.aperture_radius = 0.5;
+        camera.focal_distance = 4.0;
+        let pinhole = camera.ray_for_pixel_lens(0, 0, 0.5, 0.5, 0.5, 0.5);
+        assert_eq!(pinhole.direction, Tuple::vector(0.0, 0.0, -1.0));
+        let in_focus = pinhole.origin + pinhole.direction * 4.0;
+        let ray = camera.ray_for_pixel_lens(0, 0, 0.5, 0.5, 0.9, 0.3);
+        assert!(ray.origin != pinhole.origin);
+        let t = (in_focus - ray.origin).mag();
+        assert_eq!(ray.origin + ray.direction * t, in_focus);
+    }
+
+    #[test]
+    fn an_equirectangular_camera_sees_every_direction() {
+        let camera = Camera::new_equirectangular(400, 200);
+        let test_cases = vec![
+            (200.0, 100.0, Tuple::vector(0.0, 0.0, -1.0)),
+            (100.0, 100.0, Tuple::vector(1.0, 0.0, 0.0)),
+            (300.0, 100.0, Tuple::vector(-1.0, 0.0, 0.0)),
+            (0.0, 100.0, Tuple::vector(0.0, 0.0, 1.0)),
+            (200.0, 0.0, Tuple::vector(0.0, 1.0, 0.0)),
+            (200.0, 200.0, Tuple::vector(0.0, -1.0, 0.0)),
+            (200.0, 50.0, Tuple::vector(0.0, 2_f32.sqrt() / 2.0, -(2_f32.sqrt()) / 2.0)),
+        ];
+        for (x, y, direction) in test_cases {
+            let ray = camera.ray_for_pixel_offset(0, 0, x, y);
+            assert_eq!(ray.origin, Tuple::point(0.0, 0.0, 0.0));
+            assert_eq!(ray.direction, direction, "Pixel: {}, {}", x, y);
+        }
+    }
+
+    #[test]
+    fn an_equirectangular_camera_looking_towards_z_matches_image_backgrounds() {
+        let mut camera = Camera::new_equirectangular(64, 32);
+        camera.set_transform(&Matrix4::identity().rotate_y(PI));
+        for (x, y) in [(3, 5), (20, 16), (40, 2), (63, 30)] {
+            let ray = camera.ray_for_pixel(x, y);
+            let (u, v) = equirectangular_uv(&ray.direction);
+            assert!((u - (x as f32 + 0.5) / 64.0).abs() < 0.0001, "Pixel: {}, {}, u: {}", x, y, u);
+            assert!((v - (y as f32 + 0.5) / 32.0).abs() < 0.0001, "Pixel: {}, {}, v: {}", x, y, v);
+        }
+    }
+
+    #[test]
+    fn a_fisheye_camera_maps_distance_to_angle() {
+        let camera = Camera::new_fisheye(300, 200, PI);
+        let test_cases = vec![
+            (150.0, 100.0, Tuple::vector(0.0, 0.0, -1.0)),
+            // The edge of the image circle is at 90 degrees.
+            (250.0, 100.0, Tuple::vector(-1.0, 0.0, 0.0)),
+            (150.0, 0.0, Tuple::vector(0.0, 1.0, 0.0)),
+            (150.0, 50.0, Tuple::vector(0.0, 2_f32.sqrt() / 2.0, -(2_f32.sqrt()) / 2.0)),
+            // Outside the circle, looking backwards.
+            (0.0, 100.0, Tuple::vector((PI * 0.75).sin(), 0.0, -(PI * 0.75).cos())),
+        ];
+        for (x, y, direction) in test_cases {
+            let ray = camera.ray_for_pixel_offset(0, 0, x, y);
+            assert_eq!(ray.direction, direction, "Pixel: {}, {}", x, y);
+        }
+
+        let camera = Camera::new_fisheye(200, 200, PI / 2.0);
+        let ray = camera.ray_for_pixel_offset(0, 0, 200.0, 100.0);
+        assert_eq!(ray.direction, Tuple::vector(-(2_f32.sqrt()) / 2.0, 0.0, -(2_f32.sqrt()) / 2.0));
+    }
+
+    #[test]
+    fn a_camera_without_aperture_ignores_the_lens() {
+        let camera = Camera::new(201, 101, PI / 2_f32);
+        let pinhole = camera.ray_for_pixel(20, 30);
+        let ray = camera.ray_for_pixel_lens(20, 30, 0.5, 0.5, 0.9, 0.1);
+        assert_eq!(ray.origin, pinhole.origin);
+        assert_eq!(ray.direction, pinhole.direction);
+    }
+
+    #[test]
+    fn rays_through_the_lens_converge_on_the_focal_plane() {
+        let mut camera = Camera::new(201, 101, PI / 2_f32);
+        let transformation = Matrix4::identity()
+            .rotate_y(PI / 4_f32)
+            .translate(0_f32, -2_f32, 5_f32);
+        camera.set_transform(&transformation);
+        let pinhole = camera.ray_for_pixel(20, 30);
+        camera.aperture_radius = 0.5;
+        camera.focal_distance = 10.0;
+
+        // Where the pinhole ray meets the focal plane. The pixel is 1 unit away along -z in
+        // camera space, so the distance scales with the length of that ray.
+        let camera_direction = Tuple::vector(
+            camera.half_width - 20.5 * camera.pixel_size,
+            camera.half_height - 30.5 * camera.pixel_size,
+            -1.0,
+        );
+        let in_focus = pinhole.origin + pinhole.direction * (10.0 * camera_direction.mag());
+
+        for blades in [0, 6] {
+            camera.aperture_blades = blades;
+            for (lens_u, lens_v) in [(0.1, 0.2), (0.9, 0.5), (0.55, 0.99)] {
+                let ray = camera.ray_for_pixel_lens(20, 30, 0.5, 0.5, lens_u, lens_v);
+                assert!((ray.origin - pinhole.origin).mag() <= 0.5001);
+                assert!((ray.origin - pinhole.origin).mag() > 0.01);
+                let t = (in_focus - ray.origin).mag();
+                assert_eq!(ray.origin + ray.direction * t, in_focus);
+            }
+        }
+    }
+
+    #[test]
+    fn supersampling_spreads_rays_over_the_lens() {
+        let mut camera = Camera::new(201, 101, PI / 2_f32);
+        camera.aperture_radius = 0.25;
+        camera.focal_distance = 5.0;
+        let a = camera.ray_for_pixel_offset(20, 30, 0.25, 0.25);
+        let b = camera.ray_for_pixel_offset(20, 30, 0.75, 0.25);
+        assert!(a.origin != b.origin);
+        assert_eq!(camera.ray_for_pixel_offset(20, 30, 0.25, 0.25).origin, a.origin);
+    }
+
+    #[test]
+    fn rays_are_spread_over_the_time_the_shutter_is_open() {
+        let mut camera = Camera::new(201, 101, PI / 2_f32);
+        assert_eq!(camera.ray_for_pixel(20, 30).time, 0.0);
+        camera.shutter_open = 2.0;
+        camera.shutter_close = 2.5;
+        let pinhole = camera.ray_for_pixel_lens(20, 30, 0.5, 0.5, 0.5, 0.5);
+        let mut times = vec![];
+        for i in 0..10 {
+            let ray = camera.ray_for_pixel_offset(20, 30, i as f32 / 10.0, 0.5);
+            assert!(ray.time >= 2.0 && ray.time < 2.5);
+            assert_eq!(ray.origin, pinhole.origin);
+            times.push(ray.time);
+        }
+        assert!(times.iter().any(|time| (time - times[0]).abs() > 0.05));
+    }
+
+    #[test]
+    fn constructing_a_ray_through_an_offset_in_the_pixel() {
+        let camera = Camera::new(201, 101, PI / 2_f32);
+        assert_eq!(camera.ray_for_pixel_offset(100, 50, 0.5, 0.5).direction, camera.ray_for_pixel(100, 50).direction);
+        assert_eq!(camera.ray_for_pixel_offset(0, 0, 1.5, 0.5).direction, camera.ray_for_pixel(1, 0).direction);
+        let ray = camera.ray_for_pixel_offset(100, 50, 0.0, 0.0);
+        assert!(ray.direction.x > 0.0 && ray.direction.y > 0.0);
+    }
+
+    #[test]
+    fn a_single_centered_sample_renders_like_render_at() {
+        let world = World::half_covered();
+        let camera = Camera::new(10, 10, PI / 2_f32);
+        for x in 0..10 {
+            assert_eq!(render_pixel(x, 5, &camera, &world, &Sampler::single()), render_at(x, 5, &camera, &world));
+        }
+    }
+
+    #[test]
+    fn supersampling_blends_pixels_on_an_edge() {
+        let world = World::half_covered();
+        let camera = Camera::new(10, 10, PI / 2_f32);
+        let samplers = vec![
+            Sampler::new(SamplePattern::Grid, 4, Filter::Box),
+            Sampler::new(SamplePattern::Jittered, 4, Filter::Tent),
+            Sampler::new(SamplePattern::Random, 4, Filter::Gaussian { alpha: 2.0 }),
+            Sampler::new(SamplePattern::Jittered, 4, Filter::Mitchell { b: 1.0 / 3.0, c: 1.0 / 3.0 }),
+        ];
+        for sampler in samplers {
+            // Far from the edge, every sample sees the same thing.
+            assert_eq!(render_pixel(0, 5, &camera, &world, &sampler), WHITE);
+            assert_eq!(render_pixel(9, 5, &camera, &world, &sampler), BLACK);
+            let edge = render_pixel(4, 5, &camera, &world, &sampler);
+            assert!(edge.red > 0.05 && edge.red < 0.95, "{:?}: {:?}", sampler, edge);
+            // Rendering is repeatable.
+            assert_eq!(render_pixel(4, 5, &camera, &world, &sampler), edge);
+        }
+    }
+
+    #[test]
+    fn few_samples_of_a_filter_with_negative_weights_stay_bounded() {
+        let world = World::half_covered();
+        let camera = Camera::new(10, 10, PI / 2_f32);
+        let mitchell = Filter::Mitchell { b: 1.0 / 3.0, c: 1.0 / 3.0 };
+        let sampler = Sampler::new(SamplePattern::Jittered, 2, mitchell);
+        let (positive_area, negative_area) = mitchell.lobe_areas();
+        let brightest = positive_area / (positive_area - negative_area);
+        for y in 0..10 {
+            for x in 0..10 {
+                let color = render_pixel(x, y, &camera, &world, &sampler);
+                if x < 2 {
+                    assert_eq!(color, WHITE);
+                } else if x > 7 {
+                    assert_eq!(color, BLACK);
+                }
+                let bounded = color.red >= 1.0 - brightest - 0.0001 && color.red <= brightest + 0.0001;
+                assert!(bounded, "{}, {}: {:?}", x, y, color);
+            }
+        }
+    }
+
     /* #[test]
     fn rendering_a_world_with_a_camera() {
         let default: (Box<Sphere>, Box<Sphere>) = World::default_spheres();
@@ -1,4 +1,6 @@
 use crate::color::{Color};
+use crate::image_file::ImageError;
+use std::fmt::Write;
 use std::fs;
 
 pub struct Canvas {
@@ -33,7 +35,7 @@ pub fn set_pixel (canvas: &mut Canvas, x: usize, y: usize, color: Color) {
 }
 
 pub fn canvas_to_ppm(canvas: &Canvas) -> String {
-    let mut result = String::from(format!("P3\n{0} {1}\n255\n", canvas.width, canvas.height));    
+    let mut result = format!("P3\n{0} {1}\n255\n", canvas.width, canvas.height);
     for y in 0..canvas.height {        
         let mut line = String::with_capacity(canvas.width*3 + (canvas.width*3-1));
         for x in 0..canvas.width {
@@ -43,14 +45,27 @@ pub fn canvas_to_ppm(canvas: &Canvas) -> String {
             let b = limit((color.blue * 255.0_f32) as i32, 0, 255);
 
             if x == 0 {
-                line.push_str(&format!("{0} {1} {2}", r, g, b))
+                write!(line, "{0} {1} {2}", r, g, b).unwrap();
             }
             else {
-                line.push_str(&format!(" {0} {1} {2}", r, g, b))
+                write!(line, " {0} {1} {2}", r, g, b).unwrap();
             }
             
         }
-        result.push_str(&format!("{}\n", line));
+        result.push_str(&line);
+        result.push('\n');
+    }
+    result
+}
+
+// Binary PPM, with one byte per channel. A lot smaller and faster than P3.
+pub fn canvas_to_binary_ppm(canvas: &Canvas) -> Vec<u8> {
+    let mut result = format!("P6\n{0} {1}\n255\n", canvas.width, canvas.height).into_bytes();
+    result.reserve(canvas.width * canvas.height * 3);
+    for color in canvas.pixels.iter() {
+        result.push(limit((color.red * 255.0_f32) as i32, 0, 255) as u8);
+        result.push(limit((color.green * 255.0_f32) as i32, 0, 255) as u8);
+        result.push(limit((color.blue * 255.0_f32) as i32, 0, 255) as u8);
     }
     result
 }
@@ -60,6 +75,121 @@ pub fn canvas_to_file(canvas: &Canvas, file_name: String) {
     fs::write(file_name, canvas).expect("Unable to write cavas to ppm file.");
 }
 
+pub fn canvas_to_binary_file(canvas: &Canvas, file_name: String) {
+    let canvas = canvas_to_binary_ppm(canvas);
+    fs::write(file_name, canvas).expect("Unable to write cavas to ppm file.");
+}
+
+pub fn load_ppm(file_name: &str) -> Result<Canvas, ImageError> {
+    let bytes = fs::read(file_name).map_err(|e| ImageError::Io(e.to_string()))?;
+    ppm_to_canvas(&bytes)
+}
+
+// Reads both P3 and P6, with colors scaled by maxval to [0, 1]. P6 uses two bytes per
+// channel, most significant first, when maxval is above 255.
+pub fn ppm_to_canvas(bytes: &[u8]) -> Result<Canvas, ImageError> {
+    let mut reader = PpmReader { bytes, position: 0 };
+    let binary = match reader.token()? {
+        "P3" => false,
+        "P6" => true,
+        _ => return Err(malformed("missing P3 or P6 header")),
+    };
+    let width = reader.number(1, usize::MAX)?;
+    let height = reader.number(1, usize::MAX)?;
+    let maxval = reader.number(1, 65535)?;
+    // A single whitespace character separates the header from binary data.
+    if binary {
+        reader.bytes(1)?;
+    }
+    // Checked before allocating, so that a corrupt header gives an error rather than a
+    // canvas too large for memory. Every value takes at least one byte, even in P3.
+    let bytes_per_value = if binary && maxval > 255 { 2 } else { 1 };
+    let size = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3 * bytes_per_value));
+    match size {
+        Some(size) if size <= reader.remaining() => {}
+        _ => return Err(malformed("image larger than the file")),
+    }
+
+    let mut canvas = Canvas::new(width, height);
+    for color in canvas.pixels.iter_mut() {
+        let mut channels = [0_f32; 3];
+        for channel in channels.iter_mut() {
+            let value = if !binary {
+                reader.number(0, maxval)?
+            } else if maxval < 256 {
+                reader.bytes(1)?[0] as usize
+            } else {
+                let value = reader.bytes(2)?;
+                (value[0] as usize) << 8 | value[1] as usize
+            };
+            if value > maxval {
+                return Err(malformed("value larger than maxval"));
+            }
+            *channel = value as f32 / maxval as f32;
+        }
+        *color = Color::new(channels[0], channels[1], channels[2]);
+    }
+    Ok(canvas)
+}
+
+struct PpmReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PpmReader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], ImageError> {
+        if self.position + count > self.bytes.len() {
+            return Err(malformed("unexpected end of file"));
+        }
+        let result = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(result)
+    }
+
+    // Skips whitespace and comments, which go from # to the end of the line.
+    fn token(&mut self) -> Result<&'a str, ImageError> {
+        while self.position < self.bytes.len() {
+            let byte = self.bytes[self.position];
+            if byte == b'#' {
+                while self.position < self.bytes.len() && self.bytes[self.position] != b'\n' {
+                    self.position += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        let start = self.position;
+        while self.position < self.bytes.len() && !self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(malformed("unexpected end of file"));
+        }
+        std::str::from_utf8(&self.bytes[start..self.position]).map_err(|_| malformed("not a number"))
+    }
+
+    fn number(&mut self, min: usize, max: usize) -> Result<usize, ImageError> {
+        let token = self.token()?;
+        match token.parse::<usize>() {
+            Ok(number) if number >= min && number <= max => Ok(number),
+            _ => Err(malformed(&format!("bad number {}", token))),
+        }
+    }
+}
+
+fn malformed(message: &str) -> ImageError {
+    ImageError::Malformed(String::from(message))
+}
+
 fn limit(value: i32, min: i32, max: i32) -> i32 {
     if value < min {
         return min
@@ -73,7 +203,7 @@ fn limit(value: i32, min: i32, max: i32) -> i32 {
 #[cfg(test)]
 mod canvas_tests {
 
-    use crate::canvas::{Canvas, set_pixel, get_pixel, canvas_to_ppm};
+    use crate::canvas::{Canvas, set_pixel, get_pixel, canvas_to_ppm, canvas_to_binary_ppm, ppm_to_canvas};
     use crate::Color;
 
     #[test]
@@ -109,16 +239,72 @@ mod canvas_tests {
         set_pixel(&mut canvas, 4, 2, Color::new(-0.5_f32, 0_f32, 1_f32));
         let result = canvas_to_ppm(&canvas);
         let expected = 
-    "P3
-    5 3
-    255
-    255 0 0 0 0 0 0 0 0 0 0 0 0 0 0
-    0 0 0 0 0 0 0 127 0 0 0 0 0 0 0
-    0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n";
+"P3
+5 3
+255
+255 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+0 0 0 0 0 0 0 127 0 0 0 0 0 0 0
+0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n";
     
         assert_eq!(result, expected);
     }
-    
-    
+
+    #[test]
+    fn to_binary_ppm() {
+        let mut canvas = Canvas::new(2, 2);
+        set_pixel(&mut canvas, 0, 0, Color::new(1.5_f32, 0_f32, 0_f32));
+        set_pixel(&mut canvas, 1, 1, Color::new(-0.5_f32, 0.5_f32, 1_f32));
+        let mut expected = b"P6\n2 2\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 127, 255]);
+        assert_eq!(canvas_to_binary_ppm(&canvas), expected);
+    }
+
+    #[test]
+    fn reading_back_a_written_ppm() {
+        let mut canvas = Canvas::new(3, 2);
+        set_pixel(&mut canvas, 0, 0, Color::new(1_f32, 0.2_f32, 0_f32));
+        set_pixel(&mut canvas, 2, 1, Color::new(0_f32, 0.6_f32, 1_f32));
+        let written = vec![canvas_to_ppm(&canvas).into_bytes(), canvas_to_binary_ppm(&canvas)];
+        for bytes in written {
+            let read = ppm_to_canvas(&bytes).unwrap();
+            assert_eq!((read.width, read.height), (3, 2));
+            assert_eq!(read.pixel_at(0, 0), Color::new(1_f32, 0.2_f32, 0_f32));
+            assert_eq!(read.pixel_at(1, 0), Color::new(0_f32, 0_f32, 0_f32));
+            assert_eq!(read.pixel_at(2, 1), Color::new(0_f32, 0.6_f32, 1_f32));
+        }
+    }
+
+    #[test]
+    fn reading_a_ppm_with_comments_and_another_maxval() {
+        let bytes = b"P3\n# A comment\n2 1 # Another one\n10\n10 5 0\n# In the data\n0 2 10\n";
+        let canvas = ppm_to_canvas(bytes).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(1_f32, 0.5_f32, 0_f32));
+        assert_eq!(canvas.pixel_at(1, 0), Color::new(0_f32, 0.2_f32, 1_f32));
+    }
+
+    #[test]
+    fn reading_a_binary_ppm_with_two_bytes_per_channel() {
+        let mut bytes = b"P6 1 1 65535\n".to_vec();
+        bytes.extend_from_slice(&[255, 255, 128, 0, 0, 255]);
+        let canvas = ppm_to_canvas(&bytes).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(1_f32, 0.50001_f32, 0.00389_f32));
+    }
+
+    #[test]
+    fn malformed_ppms_give_errors() {
+        assert!(ppm_to_canvas(b"P5\n1 1\n255\n\0").is_err());
+        assert!(ppm_to_canvas(b"P3\n2 1\n255\n0 0 0\n").is_err());
+        assert!(ppm_to_canvas(b"P3\n1 1\n15\n0 16 0\n").is_err());
+        assert!(ppm_to_canvas(b"P3\n0 1\n255\n").is_err());
+        assert!(ppm_to_canvas(b"P6\n2 1\n255\n\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn ppms_larger_than_their_data_give_errors() {
+        assert!(ppm_to_canvas(b"P6 4294967296 4294967297 255\n\0\0\0").is_err());
+        assert!(ppm_to_canvas(b"P6 100000 100000 255\n\0\0\0").is_err());
+        assert!(ppm_to_canvas(b"P6 1 1 65535\n\0\0\0").is_err());
+        assert!(ppm_to_canvas(b"P3 100000 100000 255\n0 0 0").is_err());
+    }
 }
 
@@ -0,0 +1,303 @@
+use crate::math::{EPSILON, INFINITY};
+use crate::Shape;
+use crate::bounds::BoundingBox;
+use crate::material::Material;
+use crate::matrix::inverse4;
+use crate::ray::Intersection;
+use crate::Matrix4;
+use crate::Ray;
+use crate::Tuple;
+use crate::ray::transform;
+
+pub struct Cone {
+    pub id: u32,
+    pub material: Material,
+    pub transformation: Matrix4,
+    pub inverse_transformation: Matrix4,
+    pub minimum: f32,
+    pub maximum: f32,
+    pub closed: bool,
+}
+
+impl Cone {
+    pub fn new(id: u32) -> Cone {
+        Cone {
+            id,
+            material: Material::default(),
+            transformation: Matrix4::identity(),
+            inverse_transformation: Matrix4::identity(),
+            minimum: -INFINITY,
+            maximum: INFINITY,
+            closed: false,
+        }
+    }
+
+    fn intersect_caps<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if !self.closed || ray.direction.y.abs() < EPSILON {
+            return;
+        }
+
+        let t = (self.minimum - ray.origin.y) / ray.direction.y;
+        if check_cap(ray, t, self.minimum) {
+            xs.push(Intersection::new(self, t));
+        }
+
+        let t = (self.maximum - ray.origin.y) / ray.direction.y;
+        if check_cap(ray, t, self.maximum) {
+            xs.push(Intersection::new(self, t));
+        }
+    }
+
+    fn push_if_within_bounds<'a>(&'a self, ray: &Ray, t: f32, xs: &mut Vec<Intersection<'a>>) {
+        let y = ray.origin.y + t * ray.direction.y;
+        if self.minimum < y && y < self.maximum {
+            xs.push(Intersection::new(self, t));
+        }
+    }
+}
+
+impl Shape for Cone {
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.inverse_transformation = inverse4(&self.transformation)
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.inverse_transformation = inverse4(&(parent * &self.transformation))
+    }
+
+    fn normal_at(&self, world_point: &Tuple) -> Tuple {
+        let point = &self.inverse_transformation * world_point;
+        let distance = point.x.powi(2) + point.z.powi(2);
+
+        let normal: Tuple;
+        if distance < self.maximum.powi(2) && point.y >= self.maximum - EPSILON {
+            normal = Tuple::vector(0.0, 1.0, 0.0);
+        }
+        else if distance < self.minimum.powi(2) && point.y <= self.minimum + EPSILON {
+            normal = Tuple::vector(0.0, -1.0, 0.0);
+        }
+        else {
+            let mut y = distance.sqrt();
+            if point.y > 0.0 {
+                y = -y;
+            }
+            normal = Tuple::vector(point.x, y, point.z);
+        }
+
+        let mut world_normal = &self.inverse_transformation.transpose() * &normal;
+        world_normal.w = 0_f32; // Hack. Should actually find submatrix 3x3, and multiply with the inverse of that, to avoid messing with w. But this is fine and faster.
+        world_normal.normalize()
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_mut_material(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        // The double-napped cone satisfies x^2 + z^2 = y^2, with the apex in origo.
+        let ray = transform(ray, &self.inverse_transformation);
+        let mut xs = vec![];
+
+        let a = ray.direction.x.powi(2) - ray.direction.y.powi(2) + ray.direction.z.powi(2);
+        let b = 2.0 * ray.origin.x * ray.direction.x - 2.0 * ray.origin.y * ray.direction.y
+            + 2.0 * ray.origin.z * ray.direction.z;
+        let c = ray.origin.x.powi(2) - ray.origin.y.powi(2) + ray.origin.z.powi(2);
+
+        if a.abs() < EPSILON {
+            // The ray is parallel to one of the halves, so it hits the other half once.
+            // If b is zero as well, the ray passes through the apex along the surface.
+            if b.abs() >= EPSILON {
+                self.push_if_within_bounds(&ray, -c / (2.0 * b), &mut xs);
+            }
+        }
+        else {
+            let mut discriminant = b.powi(2) - 4.0 * a * c;
+            if discriminant < 0.0 {
+                // Rays grazing the apex can end up slightly negative due to rounding.
+                if discriminant > -EPSILON {
+                    discriminant = 0.0;
+                }
+                else {
+                    return xs;
+                }
+            }
+
+            let mut t0 = (-b - discriminant.sqrt()) / (2.0 * a);
+            let mut t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            self.push_if_within_bounds(&ray, t0, &mut xs);
+            self.push_if_within_bounds(&ray, t1, &mut xs);
+        }
+
+        self.intersect_caps(&ray, &mut xs);
+        xs
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let radius = f32::max(self.minimum.abs(), self.maximum.abs());
+        BoundingBox::new(
+            Tuple::point(-radius, self.minimum, -radius),
+            Tuple::point(radius, self.maximum, radius),
+        )
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix4 {
+        &self.inverse_transformation
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+// Checks if the intersection at t is within the radius of the cone at height y,
+// which is the absolute value of y.
+fn check_cap(ray: &Ray, t: f32, y: f32) -> bool {
+    let x = ray.origin.x + t * ray.direction.x;
+    let z = ray.origin.z + t * ray.direction.z;
+    (x.powi(2) + z.powi(2)) <= y.powi(2) + EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::cone::Cone;
+    use crate::Shape;
+    use crate::tuple::Tuple;
+    use crate::Matrix4;
+    use crate::Ray;
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray() {
+        let test_cases = vec![
+            (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 5.0, 5.0),
+            (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(1.0, 1.0, 1.0), 8.66025, 8.66025),
+            (Tuple::point(1.0, 1.0, -5.0), Tuple::vector(-0.5, -1.0, 1.0), 4.55006, 49.44994),
+        ];
+        let cone = Cone::new(1);
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1.normalize());
+            let xs = cone.intersections_by(&ray);
+            assert_eq!(xs.len(), 2, "Origin: {}", test_case.0);
+            assert!((xs[0].t - test_case.2).abs() < 0.001, "t0: {}", xs[0].t);
+            assert!((xs[1].t - test_case.3).abs() < 0.001, "t1: {}", xs[1].t);
+        }
+    }
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray_parallel_to_one_of_its_halves() {
+        let cone = Cone::new(1);
+        let ray = Ray::new(
+            Tuple::point(0.0, 0.0, -1.0),
+            Tuple::vector(0.0, 1.0, 1.0).normalize(),
+        );
+        let xs = cone.intersections_by(&ray);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].t - 0.35355).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_ray_along_the_surface_through_the_apex_misses_the_cone() {
+        let cone = Cone::new(1);
+        let ray = Ray::new(
+            Tuple::point(0.0, -1.0, -1.0),
+            Tuple::vector(0.0, 1.0, 1.0).normalize(),
+        );
+        let xs = cone.intersections_by(&ray);
+        assert_eq!(xs.len(), 0);
+    }
+
+    #[test]
+    fn a_ray_through_the_apex_hits_the_cone_at_the_apex() {
+        let cone = Cone::new(1);
+        let ray = Ray::new(Tuple::point(0.0, -5.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        let xs = cone.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 5.0);
+        assert_eq!(xs[1].t, 5.0);
+    }
+
+    #[test]
+    fn intersecting_a_cones_end_caps() {
+        let test_cases = vec![
+            (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0), 0),
+            (Tuple::point(0.0, 0.0, -0.25), Tuple::vector(0.0, 1.0, 1.0), 2),
+            (Tuple::point(0.0, 0.0, -0.25), Tuple::vector(0.0, 1.0, 0.0), 4),
+        ];
+        let mut cone = Cone::new(1);
+        cone.minimum = -0.5;
+        cone.maximum = 0.5;
+        cone.closed = true;
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1.normalize());
+            let xs = cone.intersections_by(&ray);
+            assert_eq!(xs.len(), test_case.2, "Origin: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn computing_the_normal_vector_on_a_cone() {
+        let test_cases = vec![
+            (Tuple::point(1.0, 1.0, 1.0), Tuple::vector(1.0, -(2_f32.sqrt()), 1.0)),
+            (Tuple::point(-1.0, -1.0, 0.0), Tuple::vector(-1.0, 1.0, 0.0)),
+        ];
+        let cone = Cone::new(1);
+
+        for test_case in test_cases {
+            let normal = cone.normal_at(&test_case.0);
+            assert_eq!(normal, test_case.1.normalize(), "Point: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn the_normal_vector_on_a_cones_end_caps() {
+        let mut cone = Cone::new(1);
+        cone.minimum = -1.0;
+        cone.maximum = 2.0;
+        cone.closed = true;
+
+        assert_eq!(cone.normal_at(&Tuple::point(0.5, 2.0, 0.0)), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(cone.normal_at(&Tuple::point(0.0, -1.0, 0.5)), Tuple::vector(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn intersecting_a_transformed_cone() {
+        let mut cone = Cone::new(1);
+        cone.minimum = 0.0;
+        cone.maximum = 1.0;
+        cone.closed = true;
+        cone.set_transformation(Matrix4::identity().translate(0.0, 1.0, 0.0));
+
+        let ray = Ray::new(Tuple::point(0.0, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let xs = cone.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[1].t, 3.0);
+        assert_eq!(cone.normal_at(&Tuple::point(0.5, 2.0, 0.0)), Tuple::vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn a_cone_has_a_bounding_box() {
+        let mut cone = Cone::new(1);
+        assert!(!cone.bounds().is_finite());
+        cone.minimum = -5.0;
+        cone.maximum = 3.0;
+        let bounds = cone.bounds();
+        assert_eq!(bounds.min, Tuple::point(-5.0, -5.0, -5.0));
+        assert_eq!(bounds.max, Tuple::point(5.0, 3.0, 5.0));
+    }
+}
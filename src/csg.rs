@@ -0,0 +1,300 @@
+use crate::Shape;
+use crate::bounds::BoundingBox;
+use crate::material::Material;
+use crate::matrix::inverse4;
+use crate::ray::Intersection;
+use crate::Matrix4;
+use crate::Ray;
+use crate::Tuple;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+// Constructive solid geometry. Like a group, the transformation is baked into the
+// children, and intersections refer to the child that was hit.
+pub struct Csg {
+    pub id: u32,
+    // Not used for shading, the children have their own materials.
+    pub material: Material,
+    pub operation: CsgOperation,
+    transformation: Matrix4,
+    parent_transformation: Matrix4,
+    inverse_transformation: Matrix4,
+    left: Box<dyn Shape>,
+    right: Box<dyn Shape>,
+}
+
+impl Csg {
+    pub fn new(id: u32, operation: CsgOperation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        Csg {
+            id,
+            material: Material::default(),
+            operation,
+            transformation: Matrix4::identity(),
+            parent_transformation: Matrix4::identity(),
+            inverse_transformation: Matrix4::identity(),
+            left,
+            right,
+        }
+    }
+
+    pub fn left(&self) -> &dyn Shape {
+        self.left.as_ref()
+    }
+
+    pub fn right(&self) -> &dyn Shape {
+        self.right.as_ref()
+    }
+
+    // Walks the sorted intersections, keeping track of whether we are inside the left
+    // and right child, and keeps the ones that are on the surface of the combined shape.
+    pub fn filter_intersections<'a>(&self, xs: Vec<Intersection<'a>>) -> Vec<Intersection<'a>> {
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut result = vec![];
+
+        for i in xs {
+            let left_hit = self.left.includes(i.obj);
+            if intersection_allowed(self.operation, left_hit, in_left, in_right) {
+                result.push(i);
+            }
+
+            if left_hit {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+            }
+        }
+        result
+    }
+
+    fn update_transformations(&mut self) {
+        let world_transformation = &self.parent_transformation * &self.transformation;
+        self.inverse_transformation = inverse4(&world_transformation);
+        self.left.set_parent_transformation(&world_transformation);
+        self.right.set_parent_transformation(&world_transformation);
+    }
+}
+
+pub fn intersection_allowed(operation: CsgOperation, left_hit: bool, in_left: bool, in_right: bool) -> bool {
+    match operation {
+        CsgOperation::Union => (left_hit && !in_right) || (!left_hit && !in_left),
+        CsgOperation::Intersection => (left_hit && in_right) || (!left_hit && in_left),
+        CsgOperation::Difference => (left_hit && !in_right) || (!left_hit && in_left),
+    }
+}
+
+impl Shape for Csg {
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.update_transformations();
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.parent_transformation = *parent;
+        self.update_transformations();
+    }
+
+    fn normal_at(&self, _: &Tuple) -> Tuple {
+        panic!("A CSG shape has no normal. Intersections always refer to the child that was hit.")
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_mut_material(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        let mut xs = self.left.intersections_by(ray);
+        xs.extend(self.right.intersections_by(ray));
+        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        let mut xs = self.filter_intersections(xs);
+        // The ray enters and leaves the combined shape, not the individual children.
+        for i in xs.iter_mut() {
+            i.container = self;
+        }
+        xs
+    }
+
+    fn build_bvh(&mut self) {
+        self.left.build_bvh();
+        self.right.build_bvh();
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.left.includes(other) || self.right.includes(other)
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = self.left.parent_space_bounds();
+        bounds.merge(&self.right.parent_space_bounds());
+        bounds
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix4 {
+        &self.inverse_transformation
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::csg::{intersection_allowed, Csg, CsgOperation};
+    use crate::cube::Cube;
+    use crate::ray::{prepare_computations, Intersection};
+    use crate::sphere::Sphere;
+    use crate::Matrix4;
+    use crate::Ray;
+    use crate::Shape;
+    use crate::tuple::Tuple;
+
+    #[test]
+    fn evaluating_the_rule_for_a_csg_operation() {
+        let test_cases = vec![
+            (CsgOperation::Union, true, true, true, false),
+            (CsgOperation::Union, true, true, false, true),
+            (CsgOperation::Union, true, false, true, false),
+            (CsgOperation::Union, true, false, false, true),
+            (CsgOperation::Union, false, true, true, false),
+            (CsgOperation::Union, false, true, false, false),
+            (CsgOperation::Union, false, false, true, true),
+            (CsgOperation::Union, false, false, false, true),
+            (CsgOperation::Intersection, true, true, true, true),
+            (CsgOperation::Intersection, true, true, false, false),
+            (CsgOperation::Intersection, true, false, true, true),
+            (CsgOperation::Intersection, true, false, false, false),
+            (CsgOperation::Intersection, false, true, true, true),
+            (CsgOperation::Intersection, false, true, false, true),
+            (CsgOperation::Intersection, false, false, true, false),
+            (CsgOperation::Intersection, false, false, false, false),
+            (CsgOperation::Difference, true, true, true, false),
+            (CsgOperation::Difference, true, true, false, true),
+            (CsgOperation::Difference, true, false, true, false),
+            (CsgOperation::Difference, true, false, false, true),
+            (CsgOperation::Difference, false, true, true, true),
+            (CsgOperation::Difference, false, true, false, true),
+            (CsgOperation::Difference, false, false, true, false),
+            (CsgOperation::Difference, false, false, false, false),
+        ];
+
+        for test_case in test_cases {
+            let result = intersection_allowed(test_case.0, test_case.1, test_case.2, test_case.3);
+            assert_eq!(result, test_case.4, "{:?}", test_case);
+        }
+    }
+
+    #[test]
+    fn filtering_a_list_of_intersections() {
+        let test_cases = vec![
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+
+        for test_case in test_cases {
+            let csg = Csg::new(1, test_case.0, Box::new(Sphere::new(2)), Box::new(Cube::new(3)));
+            let xs = vec![
+                Intersection::new(csg.left(), 1.0),
+                Intersection::new(csg.right(), 2.0),
+                Intersection::new(csg.left(), 3.0),
+                Intersection::new(csg.right(), 4.0),
+            ];
+            let expected = (xs[test_case.1].t, xs[test_case.2].t);
+            let result = csg.filter_intersections(xs);
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0].t, expected.0);
+            assert_eq!(result[1].t, expected.1);
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_csg_object() {
+        let csg = Csg::new(1, CsgOperation::Union, Box::new(Sphere::new(2)), Box::new(Cube::new(3)));
+        let ray = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(csg.intersections_by(&ray).len(), 0);
+    }
+
+    #[test]
+    fn a_ray_hits_a_csg_object() {
+        let mut right = Sphere::new(3);
+        right.set_transformation(Matrix4::identity().translate(0.0, 0.0, 0.5));
+        let csg = Csg::new(1, CsgOperation::Union, Box::new(Sphere::new(2)), Box::new(right));
+        let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = csg.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[0].obj.get_id(), 2);
+        assert_eq!(xs[1].t, 6.5);
+        assert_eq!(xs[1].obj.get_id(), 3);
+    }
+
+    #[test]
+    fn carving_a_hole_in_a_transformed_cube() {
+        let mut hole = Sphere::new(3);
+        hole.set_transformation(Matrix4::identity().scale(1.3, 1.3, 1.3));
+        let mut csg = Csg::new(1, CsgOperation::Difference, Box::new(Cube::new(2)), Box::new(hole));
+        csg.set_transformation(Matrix4::identity().translate(0.0, 0.0, 10.0));
+
+        // Through the middle, the sphere removes everything.
+        let ray = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(csg.intersections_by(&ray).len(), 0);
+
+        // Through a corner, only the cube is left.
+        let ray = Ray::new(Tuple::point(0.95, 0.95, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = csg.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 9.0);
+        assert_eq!(xs[0].obj.normal_at(&ray.position(xs[0].t)), Tuple::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn finding_n1_and_n2_in_a_glass_csg_lens() {
+        // Two overlapping glass spheres intersected into a lens, inside a bigger sphere of water.
+        let mut left = Sphere::new_glass(2);
+        left.set_transformation(Matrix4::identity().translate(0.0, 0.0, 0.5));
+        let mut right = Sphere::new_glass(3);
+        right.set_transformation(Matrix4::identity().translate(0.0, 0.0, -0.5));
+        let lens = Csg::new(1, CsgOperation::Intersection, Box::new(left), Box::new(right));
+        let mut water = Sphere::new_glass(4);
+        water.material.refractive_index = 1.33;
+        water.set_transformation(Matrix4::identity().scale(3.0, 3.0, 3.0));
+
+        let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = lens.intersections_by(&ray);
+        xs.extend(water.intersections_by(&ray));
+        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        assert_eq!(xs.len(), 4);
+
+        let scenarios = [(1.0, 1.33), (1.33, 1.5), (1.5, 1.33), (1.33, 1.0)];
+        for i in 0..4 {
+            let comps = prepare_computations(&xs[i], &ray, &xs);
+            assert_eq!(comps.n1, scenarios[i].0, "Intersection {}", i);
+            assert_eq!(comps.n2, scenarios[i].1, "Intersection {}", i);
+        }
+    }
+
+    #[test]
+    fn a_csg_shape_has_a_bounding_box_that_contains_its_children() {
+        let mut right = Sphere::new(3);
+        right.set_transformation(Matrix4::identity().translate(2.0, 3.0, 4.0));
+        let csg = Csg::new(1, CsgOperation::Difference, Box::new(Sphere::new(2)), Box::new(right));
+        let bounds = csg.bounds();
+        assert_eq!(bounds.min, Tuple::point(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.max, Tuple::point(3.0, 4.0, 5.0));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::math::{EPSILON, INFINITY};
 use crate::Shape;
+use crate::bounds::BoundingBox;
 use crate::material::Material;
 use crate::matrix::inverse4;
 use crate::ray::Intersection;
@@ -11,6 +12,7 @@ use crate::ray::transform;
 pub struct Cube {
     pub id: u32,
     pub material: Material,
+    pub transformation: Matrix4,
     pub inverse_transformation: Matrix4,
 }
 
@@ -19,14 +21,20 @@ impl Cube {
         Cube {
             id,
             material: Material::default(),
+            transformation: Matrix4::identity(),
             inverse_transformation: Matrix4::identity()
         }
     }
 }
 
 impl Shape for Cube {    
-    fn set_transformation(&mut self, t: Matrix4) {        
-        self.inverse_transformation = inverse4(&t)
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.inverse_transformation = inverse4(&self.transformation)
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.inverse_transformation = inverse4(&(parent * &self.transformation))
     }
 
     fn normal_at(&self, world_point: &Tuple) -> Tuple {
@@ -70,18 +78,20 @@ impl Shape for Cube {
             return vec![]
         }
 
-        let i_min = Intersection {
-            obj: self,
-            t: tmin
-        };
-        let i_max = Intersection {
-            obj: self,
-            t: tmax
-        };        
+        let i_min = Intersection::new(self, tmin);
+        let i_max = Intersection::new(self, tmax);        
 
         vec![i_min, i_max]
     }
 
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0))
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
     fn get_inverse_transformation(&self) -> &Matrix4 {
         &self.inverse_transformation
     }
@@ -184,4 +194,12 @@ mod tests {
             assert_eq!(normal, test_case.1, "Point: {}", test_case.0);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_cube_has_a_bounding_box() {
+        let cube = Cube::new(1);
+        let bounds = cube.bounds();
+        assert_eq!(bounds.min, Tuple::point(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.max, Tuple::point(1.0, 1.0, 1.0));
+    }
+}
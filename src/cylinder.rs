@@ -0,0 +1,315 @@
+use crate::math::{EPSILON, INFINITY};
+use crate::Shape;
+use crate::bounds::BoundingBox;
+use crate::material::Material;
+use crate::matrix::inverse4;
+use crate::ray::Intersection;
+use crate::Matrix4;
+use crate::Ray;
+use crate::Tuple;
+use crate::ray::transform;
+
+pub struct Cylinder {
+    pub id: u32,
+    pub material: Material,
+    pub transformation: Matrix4,
+    pub inverse_transformation: Matrix4,
+    pub minimum: f32,
+    pub maximum: f32,
+    pub closed: bool,
+}
+
+impl Cylinder {
+    pub fn new(id: u32) -> Cylinder {
+        Cylinder {
+            id,
+            material: Material::default(),
+            transformation: Matrix4::identity(),
+            inverse_transformation: Matrix4::identity(),
+            minimum: -INFINITY,
+            maximum: INFINITY,
+            closed: false,
+        }
+    }
+
+    fn intersect_caps<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if !self.closed || ray.direction.y.abs() < EPSILON {
+            return;
+        }
+
+        let t = (self.minimum - ray.origin.y) / ray.direction.y;
+        if check_cap(ray, t) {
+            xs.push(Intersection::new(self, t));
+        }
+
+        let t = (self.maximum - ray.origin.y) / ray.direction.y;
+        if check_cap(ray, t) {
+            xs.push(Intersection::new(self, t));
+        }
+    }
+}
+
+impl Shape for Cylinder {
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.inverse_transformation = inverse4(&self.transformation)
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.inverse_transformation = inverse4(&(parent * &self.transformation))
+    }
+
+    fn normal_at(&self, world_point: &Tuple) -> Tuple {
+        let point = &self.inverse_transformation * world_point;
+        let distance = point.x.powi(2) + point.z.powi(2);
+
+        let normal: Tuple;
+        if distance < 1.0 && point.y >= self.maximum - EPSILON {
+            normal = Tuple::vector(0.0, 1.0, 0.0);
+        }
+        else if distance < 1.0 && point.y <= self.minimum + EPSILON {
+            normal = Tuple::vector(0.0, -1.0, 0.0);
+        }
+        else {
+            normal = Tuple::vector(point.x, 0.0, point.z);
+        }
+
+        let mut world_normal = &self.inverse_transformation.transpose() * &normal;
+        world_normal.w = 0_f32; // Hack. Should actually find submatrix 3x3, and multiply with the inverse of that, to avoid messing with w. But this is fine and faster.
+        world_normal.normalize()
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_mut_material(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        // A point on the infinite cylinder satisfies x^2 + z^2 = 1, so only
+        // the x and z components of the ray take part in the quadratic.
+        let ray = transform(ray, &self.inverse_transformation);
+        let mut xs = vec![];
+
+        let a = ray.direction.x.powi(2) + ray.direction.z.powi(2);
+        if a.abs() >= EPSILON {
+            let b = 2.0 * ray.origin.x * ray.direction.x + 2.0 * ray.origin.z * ray.direction.z;
+            let c = ray.origin.x.powi(2) + ray.origin.z.powi(2) - 1.0;
+            let discriminant = b.powi(2) - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return xs;
+            }
+
+            let mut t0 = (-b - discriminant.sqrt()) / (2.0 * a);
+            let mut t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            let y0 = ray.origin.y + t0 * ray.direction.y;
+            if self.minimum < y0 && y0 < self.maximum {
+                xs.push(Intersection::new(self, t0));
+            }
+
+            let y1 = ray.origin.y + t1 * ray.direction.y;
+            if self.minimum < y1 && y1 < self.maximum {
+                xs.push(Intersection::new(self, t1));
+            }
+        }
+
+        self.intersect_caps(&ray, &mut xs);
+        xs
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1.0, self.minimum, -1.0), Tuple::point(1.0, self.maximum, 1.0))
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix4 {
+        &self.inverse_transformation
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+// Checks if the intersection at t is within the radius (1) of the cylinder.
+fn check_cap(ray: &Ray, t: f32) -> bool {
+    let x = ray.origin.x + t * ray.direction.x;
+    let z = ray.origin.z + t * ray.direction.z;
+    (x.powi(2) + z.powi(2)) <= 1.0 + EPSILON
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::cylinder::Cylinder;
+    use crate::math::INFINITY;
+    use crate::Shape;
+    use crate::tuple::Tuple;
+    use crate::Matrix4;
+    use crate::Ray;
+
+    #[test]
+    fn a_ray_misses_a_cylinder() {
+        let test_cases = vec![
+            (Tuple::point(1.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(1.0, 1.0, 1.0)),
+        ];
+        let cylinder = Cylinder::new(1);
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1.normalize());
+            let xs = cylinder.intersections_by(&ray);
+            assert_eq!(xs.len(), 0);
+        }
+    }
+
+    #[test]
+    fn a_ray_strikes_a_cylinder() {
+        let test_cases = vec![
+            (Tuple::point(1.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 5.0, 5.0),
+            (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 4.0, 6.0),
+            (Tuple::point(0.5, 0.0, -5.0), Tuple::vector(0.1, 1.0, 1.0), 6.80798, 7.08872),
+        ];
+        let cylinder = Cylinder::new(1);
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1.normalize());
+            let xs = cylinder.intersections_by(&ray);
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0].t - test_case.2).abs() < 0.0001);
+            assert!((xs[1].t - test_case.3).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn normal_vector_on_a_cylinder() {
+        let test_cases = vec![
+            (Tuple::point(1.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0)),
+            (Tuple::point(0.0, 5.0, -1.0), Tuple::vector(0.0, 0.0, -1.0)),
+            (Tuple::point(0.0, -2.0, 1.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(-1.0, 1.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+        ];
+        let cylinder = Cylinder::new(1);
+
+        for test_case in test_cases {
+            let normal = cylinder.normal_at(&test_case.0);
+            assert_eq!(normal, test_case.1, "Point: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn the_default_minimum_and_maximum_for_a_cylinder() {
+        let cylinder = Cylinder::new(1);
+        assert_eq!(cylinder.minimum, -INFINITY);
+        assert_eq!(cylinder.maximum, INFINITY);
+        assert!(!cylinder.closed);
+    }
+
+    #[test]
+    fn intersecting_a_constrained_cylinder() {
+        let test_cases = vec![
+            (Tuple::point(0.0, 1.5, 0.0), Tuple::vector(0.1, 1.0, 0.0), 0),
+            (Tuple::point(0.0, 3.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
+            (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
+            (Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
+            (Tuple::point(0.0, 1.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), 0),
+            (Tuple::point(0.0, 1.5, -2.0), Tuple::vector(0.0, 0.0, 1.0), 2),
+        ];
+        let mut cylinder = Cylinder::new(1);
+        cylinder.minimum = 1.0;
+        cylinder.maximum = 2.0;
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1.normalize());
+            let xs = cylinder.intersections_by(&ray);
+            assert_eq!(xs.len(), test_case.2, "Origin: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn intersecting_the_caps_of_a_closed_cylinder() {
+        let test_cases = vec![
+            (Tuple::point(0.0, 3.0, 0.0), Tuple::vector(0.0, -1.0, 0.0), 2),
+            (Tuple::point(0.0, 3.0, -2.0), Tuple::vector(0.0, -1.0, 2.0), 2),
+            (Tuple::point(0.0, 4.0, -2.0), Tuple::vector(0.0, -1.0, 1.0), 2),
+            (Tuple::point(0.0, 0.0, -2.0), Tuple::vector(0.0, 1.0, 2.0), 2),
+            (Tuple::point(0.0, -1.0, -2.0), Tuple::vector(0.0, 1.0, 1.0), 2),
+        ];
+        let mut cylinder = Cylinder::new(1);
+        cylinder.minimum = 1.0;
+        cylinder.maximum = 2.0;
+        cylinder.closed = true;
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1.normalize());
+            let xs = cylinder.intersections_by(&ray);
+            assert_eq!(xs.len(), test_case.2, "Origin: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn the_normal_vector_on_a_cylinders_end_caps() {
+        let test_cases = vec![
+            (Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(0.5, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(0.0, 1.0, 0.5), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(0.0, 2.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.5, 2.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.0, 2.0, 0.5), Tuple::vector(0.0, 1.0, 0.0)),
+        ];
+        let mut cylinder = Cylinder::new(1);
+        cylinder.minimum = 1.0;
+        cylinder.maximum = 2.0;
+        cylinder.closed = true;
+
+        for test_case in test_cases {
+            let normal = cylinder.normal_at(&test_case.0);
+            assert_eq!(normal, test_case.1, "Point: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn the_normal_on_a_transformed_cylinder() {
+        let mut cylinder = Cylinder::new(1);
+        cylinder.minimum = 1.0;
+        cylinder.maximum = 2.0;
+        cylinder.closed = true;
+        cylinder.set_transformation(
+            Matrix4::identity()
+                .translate(0.0, 1.0, 0.0)
+                .scale(2.0, 1.0, 2.0),
+        );
+
+        let side = cylinder.normal_at(&Tuple::point(2.0, 2.5, 0.0));
+        assert_eq!(side, Tuple::vector(1.0, 0.0, 0.0));
+        let top = cylinder.normal_at(&Tuple::point(1.0, 3.0, 0.0));
+        assert_eq!(top, Tuple::vector(0.0, 1.0, 0.0));
+
+        let ray = Ray::new(Tuple::point(0.0, 2.5, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = cylinder.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 3.0);
+        assert_eq!(xs[1].t, 7.0);
+    }
+
+    #[test]
+    fn a_cylinder_has_a_bounding_box() {
+        let mut cylinder = Cylinder::new(1);
+        assert!(!cylinder.bounds().is_finite());
+        cylinder.minimum = -5.0;
+        cylinder.maximum = 3.0;
+        let bounds = cylinder.bounds();
+        assert_eq!(bounds.min, Tuple::point(-1.0, -5.0, -1.0));
+        assert_eq!(bounds.max, Tuple::point(1.0, 3.0, 1.0));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::background::{equirectangular_color_at, equirectangular_uv};
+use crate::canvas::Canvas;
+use crate::math::{INFINITY, PI};
+use crate::random::Rng;
+use crate::ray::{Computation, Ray};
+use crate::tuple::reflect;
+use crate::world::{is_occluded, World};
+use crate::Color;
+use crate::Tuple;
+use crate::BLACK;
+
+// Piecewise constant distribution over [0, 1), to pick values with a probability
+// proportional to func.
+struct Distribution {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution {
+    fn new(func: Vec<f32>) -> Distribution {
+        let n = func.len();
+        let mut sums = vec![0_f64; n + 1];
+        for i in 0..n {
+            sums[i + 1] = sums[i] + func[i] as f64 / n as f64;
+        }
+        let integral = sums[n];
+        // Nothing to prefer, so pick uniformly.
+        let cdf = if integral > 0.0 {
+            sums.iter().map(|sum| (sum / integral) as f32).collect()
+        } else {
+            (0..=n).map(|i| i as f32 / n as f32).collect()
+        };
+        Distribution {
+            func,
+            cdf,
+            integral: integral as f32,
+        }
+    }
+
+    fn pdf(&self, index: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.func[index] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    // The picked value, its probability density, and the index of the piece it is in.
+    fn sample(&self, u: f32) -> (f32, f32, usize) {
+        let n = self.func.len();
+        let index = usize::min(self.cdf.partition_point(|&c| c <= u).saturating_sub(1), n - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+        ((index as f32 + offset) / n as f32, self.pdf(index), index)
+    }
+}
+
+// Lights the scene from an equirectangular image surrounding it, e.g. a studio HDRI.
+// Directions are sampled by the brightness of the image, so small bright light sources
+// in it are found with few samples.
+pub struct EnvironmentLight {
+    image: Canvas,
+    // Scales the radiance of the image.
+    pub intensity: f32,
+    // Samples per shaded point, for each of the diffuse and glossy lighting.
+    pub samples: u32,
+    rows: Vec<Distribution>,
+    marginal: Distribution,
+}
+
+impl EnvironmentLight {
+    pub fn new(image: Canvas) -> EnvironmentLight {
+        // There would be no directions to sample.
+        assert!(image.width > 0 && image.height > 0, "The environment image is empty");
+        let mut rows = Vec::with_capacity(image.height);
+        for y in 0..image.height {
+            // Rows near the poles cover less of the sphere, so they are picked less often.
+            let sin_theta = (PI * (y as f32 + 0.5) / image.height as f32).sin();
+            let func = (0..image.width)
+                .map(|x| luminance(&image.pixel_at(x, y)) * sin_theta)
+                .collect();
+            rows.push(Distribution::new(func));
+        }
+        let marginal = Distribution::new(rows.iter().map(|row| row.integral).collect());
+        EnvironmentLight {
+            image,
+            intensity: 1.0,
+            samples: 64,
+            rows,
+            marginal,
+        }
+    }
+
+    pub fn image(&self) -> &Canvas {
+        &self.image
+    }
+
+    pub fn radiance(&self, direction: &Tuple) -> Color {
+        equirectangular_color_at(&self.image, direction) * self.intensity
+    }
+
+    // A direction picked by brightness, and its probability density per solid angle.
+    pub fn sample(&self, u1: f32, u2: f32) -> (Tuple, f32) {
+        let (v, pdf_v, row) = self.marginal.sample(u1);
+        let (u, pdf_u, _) = self.rows[row].sample(u2);
+        let theta = v * PI;
+        let phi = (u - 0.5) * 2.0 * PI;
+        let sin_theta = theta.sin();
+        let direction = Tuple::vector(sin_theta * phi.sin(), theta.cos(), sin_theta * phi.cos());
+        if sin_theta <= 0.0 {
+            return (direction, 0.0);
+        }
+        (direction, pdf_u * pdf_v / (2.0 * PI * PI * sin_theta))
+    }
+
+    pub fn pdf(&self, direction: &Tuple) -> f32 {
+        let (u, v) = equirectangular_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let row = usize::min((v * self.rows.len() as f32) as usize, self.rows.len() - 1);
+        let column = usize::min((u * self.image.width as f32) as usize, self.image.width - 1);
+        self.marginal.pdf(row) * self.rows[row].pdf(column) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+fn luminance(color: &Color) -> f32 {
+    0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+}
+
+// Diffuse and glossy lighting from the environment. Diffuse samples are picked by the
+// brightness of the environment, and glossy ones around the mirror direction following
+// the (normalized) Phong lobe of the material. Both check for shadows on their own.
+pub fn environment_lighting(world: &World, environment: &EnvironmentLight, comps: &Computation) -> Color {
+    let material = comps.object.get_material();
+    let color = match &material.pattern {
+        Some(p) => p.color_at_obj(comps.object, &comps.over_point),
+        None => material.color,
+    };
+    let point = comps.over_point;
+    let normal = comps.surface_normalv;
+    let samples = u32::max(environment.samples, 1);
+    let mut rng = Rng::from_floats(&[point.x, point.y, point.z]);
+
+    let mut diffuse = BLACK;
+    if material.diffuse > 0.0 {
+        for _ in 0..samples {
+            let (direction, pdf) = environment.sample(rng.next_f32(), rng.next_f32());
+            let cos_theta = direction.dot(&normal);
+            if pdf <= 0.0 || cos_theta <= 0.0 || is_occluded(world, &Ray::new_at_time(point, direction, comps.time), INFINITY) {
+                continue;
+            }
+            diffuse = diffuse + environment.radiance(&direction) * (cos_theta / (PI * pdf));
+        }
+        diffuse = color * diffuse * (material.diffuse / samples as f32);
+    }
+
+    let mut glossy = BLACK;
+    if material.specular > 0.0 {
+        let reflectv = reflect(&(-comps.eye_direction), &normal);
+        let (tangent, bitangent) = orthonormal_basis(&reflectv);
+        let exponent = material.shininess;
+        for _ in 0..samples {
+            let cos_alpha = rng.next_f32().powf(1.0 / (exponent + 1.0));
+            let sin_alpha = f32::max(1.0 - cos_alpha * cos_alpha, 0.0).sqrt();
+            let phi = 2.0 * PI * rng.next_f32();
+            let direction =
+                tangent * (sin_alpha * phi.cos()) + bitangent * (sin_alpha * phi.sin()) + reflectv * cos_alpha;
+            let cos_theta = direction.dot(&normal);
+            if cos_theta <= 0.0 || is_occluded(world, &Ray::new_at_time(point, direction, comps.time), INFINITY) {
+                continue;
+            }
+            glossy = glossy + environment.radiance(&direction) * cos_theta;
+        }
+        glossy = glossy * (material.specular * (exponent + 2.0) / (exponent + 1.0) / samples as f32);
+    }
+
+    diffuse + glossy
+}
+
+fn orthonormal_basis(v: &Tuple) -> (Tuple, Tuple) {
+    let helper = if v.x.abs() > 0.9 {
+        Tuple::vector(0.0, 1.0, 0.0)
+    } else {
+        Tuple::vector(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross(v).normalize();
+    let bitangent = v.cross(&tangent);
+    (tangent, bitangent)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canvas::Canvas;
+    use crate::environment::{environment_lighting, Distribution, EnvironmentLight};
+    use crate::lighting::PointLight;
+    use crate::math::PI;
+    use crate::plane::Plane;
+    use crate::random::Rng;
+    use crate::ray::{prepare_computations, Intersection};
+    use crate::sphere::Sphere;
+    use crate::world::{color_at, World};
+    use crate::Color;
+    use crate::Matrix4;
+    use crate::Ray;
+    use crate::Shape;
+    use crate::Tuple;
+
+    fn uniform_environment(width: usize, height: usize, color: Color) -> EnvironmentLight {
+        let mut image = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set_pixel(x, y, color);
+            }
+        }
+        EnvironmentLight::new(image)
+    }
+
+    #[test]
+    fn sampling_a_piecewise_constant_distribution() {
+        let distribution = Distribution::new(vec![1.0, 3.0]);
+        let (x, pdf, index) = distribution.sample(0.125);
+        assert!((x - 0.25).abs() < 0.0001);
+        assert_eq!(pdf, 0.5);
+        assert_eq!(index, 0);
+        let (x, pdf, index) = distribution.sample(0.625);
+        assert!((x - 0.75).abs() < 0.0001);
+        assert_eq!(pdf, 1.5);
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The environment image is empty")]
+    fn an_environment_needs_an_image() {
+        EnvironmentLight::new(Canvas::new(0, 4));
+    }
+
+    #[test]
+    fn an_environment_is_sampled_by_brightness() {
+        // Black, except for one bright pixel looking towards +x.
+        let mut image = Canvas::new(16, 8);
+        image.set_pixel(12, 4, Color::new(10.0, 10.0, 10.0));
+        let environment = EnvironmentLight::new(image);
+
+        let mut rng = Rng::new(1);
+        for _ in 0..100 {
+            let (direction, pdf) = environment.sample(rng.next_f32(), rng.next_f32());
+            assert!(direction.x > 0.8, "Direction: {}", direction);
+            assert!((pdf - environment.pdf(&direction)).abs() / pdf < 0.001);
+            assert_eq!(environment.radiance(&direction), Color::new(10.0, 10.0, 10.0));
+        }
+    }
+
+    #[test]
+    fn a_uniform_environment_is_sampled_almost_uniformly_over_the_sphere() {
+        let environment = uniform_environment(64, 32, Color::new(1.0, 1.0, 1.0));
+        let mut rng = Rng::new(2);
+        for _ in 0..100 {
+            let (direction, pdf) = environment.sample(rng.next_f32(), rng.next_f32());
+            assert!((direction.mag() - 1.0).abs() < 0.0001);
+            assert!((pdf - environment.pdf(&direction)).abs() / pdf < 0.001);
+            // Within a row the density changes a little, most near the poles.
+            if direction.y.abs() < 0.9 {
+                assert!((pdf * 4.0 * PI - 1.0).abs() < 0.1, "Direction: {}, pdf: {}", direction, pdf);
+            }
+        }
+    }
+
+    fn world_in_environment(mut environment: EnvironmentLight) -> World {
+        environment.samples = 4096;
+        let mut world = World::new(PointLight::new(Tuple::point(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)));
+        world.lights = vec![];
+        world.environment = Some(environment);
+        let mut floor = Plane::new(1);
+        floor.material.ambient = 0.0;
+        floor.material.specular = 0.0;
+        world.add_object(floor);
+        world
+    }
+
+    #[test]
+    fn a_white_environment_lights_a_surface_like_a_light_straight_above_it() {
+        let world = world_in_environment(uniform_environment(64, 32, Color::new(1.0, 1.0, 1.0)));
+        let ray = Ray::new(Tuple::point(0.0, 1.0, -1.0), Tuple::vector(0.0, -1.0, 1.0).normalize());
+        let color = color_at(&world, &ray, 0);
+        assert!((color.red - 0.9).abs() < 0.05, "Color: {}", color);
+        assert!((color.blue - 0.9).abs() < 0.05, "Color: {}", color);
+    }
+
+    #[test]
+    fn objects_block_the_light_of_the_environment() {
+        let mut world = world_in_environment(uniform_environment(64, 32, Color::new(1.0, 1.0, 1.0)));
+        let mut sphere = Sphere::new(2);
+        sphere.set_transformation(Matrix4::identity().translate(0.0, 2.0, 0.0).scale(1.5, 1.5, 1.5));
+        world.add_object(sphere);
+
+        let ray = Ray::new(Tuple::point(0.0, 0.5, -1.0), Tuple::vector(0.0, -1.0, 2.0).normalize());
+        let color = color_at(&world, &ray, 0);
+        assert!(color.red < 0.7, "Color: {}", color);
+    }
+
+    #[test]
+    fn a_glossy_surface_reflects_the_bright_part_of_the_environment() {
+        // The upper half of the environment is bright.
+        let mut image = Canvas::new(64, 32);
+        for y in 0..16 {
+            for x in 0..64 {
+                image.set_pixel(x, y, Color::new(1.0, 1.0, 1.0));
+            }
+        }
+        let world = world_in_environment(EnvironmentLight::new(image));
+        let mut glossy = Plane::new(2);
+        glossy.material.diffuse = 0.0;
+        glossy.material.specular = 1.0;
+        glossy.material.shininess = 50.0;
+
+        let ray = Ray::new(Tuple::point(0.0, 1.0, -1.0), Tuple::vector(0.0, -1.0, 1.0).normalize());
+        let i = Intersection::new(&glossy, 2_f32.sqrt());
+        let comps = prepare_computations(&i, &ray, &vec![]);
+        let color = environment_lighting(&world, world.environment.as_ref().unwrap(), &comps);
+        // The mirror direction is 45 degrees up, so the lobe mostly sees the bright half.
+        assert!((color.red - 0.71).abs() < 0.05, "Color: {}", color);
+
+        let ray = Ray::new(Tuple::point(0.0, -1.0, -1.0), Tuple::vector(0.0, 1.0, 1.0).normalize());
+        let i = Intersection::new(&glossy, 2_f32.sqrt());
+        let comps = prepare_computations(&i, &ray, &vec![]);
+        let color = environment_lighting(&world, world.environment.as_ref().unwrap(), &comps);
+        assert_eq!(color, Color::new(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::Shape;
+use crate::bounds::BoundingBox;
+use crate::bvh::Bvh;
+use crate::material::Material;
+use crate::matrix::inverse4;
+use crate::ray::{transform, Intersection};
+use crate::Matrix4;
+use crate::Ray;
+use crate::Tuple;
+
+// A group has no surface of its own. Its transformation is baked into the children,
+// so they intersect, find normals and look up patterns in world space on their own.
+pub struct Group {
+    pub id: u32,
+    // Not used for shading, every child has its own material.
+    pub material: Material,
+    transformation: Matrix4,
+    parent_transformation: Matrix4,
+    inverse_transformation: Matrix4,
+    children: Vec<Box<dyn Shape>>,
+    bvh: Option<Bvh>,
+}
+
+impl Group {
+    pub fn new(id: u32) -> Group {
+        Group {
+            id,
+            material: Material::default(),
+            transformation: Matrix4::identity(),
+            parent_transformation: Matrix4::identity(),
+            inverse_transformation: Matrix4::identity(),
+            children: vec![],
+            bvh: None,
+        }
+    }
+
+    pub fn add_child(&mut self, mut child: Box<dyn Shape>) {
+        child.set_parent_transformation(&self.world_transformation());
+        self.children.push(child);
+        self.bvh = None;
+    }
+
+    pub fn children(&self) -> &Vec<Box<dyn Shape>> {
+        &self.children
+    }
+
+    fn world_transformation(&self) -> Matrix4 {
+        &self.parent_transformation * &self.transformation
+    }
+
+    fn update_transformations(&mut self) {
+        let world_transformation = self.world_transformation();
+        self.inverse_transformation = inverse4(&world_transformation);
+        for child in self.children.iter_mut() {
+            child.set_parent_transformation(&world_transformation);
+        }
+    }
+}
+
+impl Shape for Group {
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.update_transformations();
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.parent_transformation = *parent;
+        self.update_transformations();
+    }
+
+    fn normal_at(&self, _: &Tuple) -> Tuple {
+        panic!("A group has no normal. Intersections always refer to the child that was hit.")
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_mut_material(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        let mut xs: Vec<_> = match &self.bvh {
+            // The children's bounds are in the group's space, while the children
+            // themselves are intersected in world space.
+            Some(bvh) => bvh.intersect(&self.children, &transform(ray, &self.inverse_transformation), ray),
+            None => self
+                .children
+                .iter()
+                .flat_map(|child| child.intersections_by(ray))
+                .collect(),
+        };
+        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        xs
+    }
+
+    fn occludes(&self, ray: &Ray, max_distance: f32) -> bool {
+        match &self.bvh {
+            Some(bvh) => bvh.occludes(
+                &self.children,
+                &transform(ray, &self.inverse_transformation),
+                ray,
+                max_distance,
+            ),
+            None => self
+                .children
+                .iter()
+                .any(|child| child.occludes(ray, max_distance)),
+        }
+    }
+
+    fn build_bvh(&mut self) {
+        for child in self.children.iter_mut() {
+            child.build_bvh();
+        }
+        self.bvh = Some(Bvh::build(&self.children));
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.children.iter().any(|child| child.includes(other))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        for child in self.children.iter() {
+            bounds.merge(&child.parent_space_bounds());
+        }
+        bounds
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix4 {
+        &self.inverse_transformation
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::cylinder::Cylinder;
+    use crate::color::{BLACK, WHITE};
+    use crate::group::Group;
+    use crate::pattern::{Pattern, StripePattern};
+    use crate::sphere::Sphere;
+    use crate::Matrix4;
+    use crate::Ray;
+    use crate::Shape;
+    use crate::tuple::Tuple;
+    use core::f32::consts::PI;
+
+    #[test]
+    fn intersecting_a_ray_with_an_empty_group() {
+        let group = Group::new(1);
+        let ray = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(group.intersections_by(&ray).len(), 0);
+    }
+
+    #[test]
+    fn intersecting_a_ray_with_a_nonempty_group() {
+        let mut group = Group::new(1);
+        let s1 = Sphere::new(2);
+        let mut s2 = Sphere::new(3);
+        s2.set_transformation(Matrix4::identity().translate(0.0, 0.0, -3.0));
+        let mut s3 = Sphere::new(4);
+        s3.set_transformation(Matrix4::identity().translate(5.0, 0.0, 0.0));
+        group.add_child(Box::new(s1));
+        group.add_child(Box::new(s2));
+        group.add_child(Box::new(s3));
+
+        let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = group.intersections_by(&ray);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].obj.get_id(), 3);
+        assert_eq!(xs[1].obj.get_id(), 3);
+        assert_eq!(xs[2].obj.get_id(), 2);
+        assert_eq!(xs[3].obj.get_id(), 2);
+    }
+
+    #[test]
+    fn intersecting_a_transformed_group() {
+        let mut group = Group::new(1);
+        group.set_transformation(Matrix4::identity().scale(2.0, 2.0, 2.0));
+        let mut sphere = Sphere::new(2);
+        sphere.set_transformation(Matrix4::identity().translate(5.0, 0.0, 0.0));
+        group.add_child(Box::new(sphere));
+
+        let ray = Ray::new(Tuple::point(10.0, 0.0, -10.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(group.intersections_by(&ray).len(), 2);
+    }
+
+    #[test]
+    fn transforming_a_group_after_adding_children_moves_them() {
+        let mut group = Group::new(1);
+        group.add_child(Box::new(Sphere::new(2)));
+        group.set_transformation(Matrix4::identity().translate(0.0, 5.0, 0.0));
+
+        let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(group.intersections_by(&ray).len(), 0);
+        let ray = Ray::new(Tuple::point(0.0, 5.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(group.intersections_by(&ray).len(), 2);
+    }
+
+    #[test]
+    fn a_group_includes_its_descendants() {
+        let mut inner = Group::new(2);
+        inner.add_child(Box::new(Sphere::new(3)));
+        let mut outer = Group::new(1);
+        outer.add_child(Box::new(inner));
+
+        assert!(outer.includes(&Sphere::new(3)));
+        assert!(!outer.includes(&Sphere::new(4)));
+    }
+
+    fn nested_sphere() -> Group {
+        let mut outer = Group::new(1);
+        outer.set_transformation(Matrix4::identity().rotate_y(PI / 2.0));
+        let mut inner = Group::new(2);
+        inner.set_transformation(Matrix4::identity().scale(1.0, 2.0, 3.0));
+        let mut sphere = Sphere::new(3);
+        sphere.set_transformation(Matrix4::identity().translate(5.0, 0.0, 0.0));
+        inner.add_child(Box::new(sphere));
+        outer.add_child(Box::new(inner));
+        outer
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_child_object() {
+        let outer = nested_sphere();
+        let ray = Ray::new(Tuple::point(1.7321, 1.1547, 20.0), Tuple::vector(0.0, 0.0, -1.0));
+        let xs = outer.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        let sphere = xs[0].obj;
+        assert_eq!(sphere.get_id(), 3);
+        let normal = sphere.normal_at(&Tuple::point(1.7321, 1.1547, -5.5774));
+        assert_eq!(normal, Tuple::vector(0.2857, 0.4286, -0.8571));
+    }
+
+    #[test]
+    fn patterns_on_a_child_object_are_looked_up_through_the_parents() {
+        let mut group = Group::new(1);
+        group.set_transformation(Matrix4::identity().scale(2.0, 2.0, 2.0));
+        group.add_child(Box::new(Sphere::new(2)));
+        let pattern = StripePattern::test_default();
+
+        // 1.5 in world space is 0.75 in object space, so we are still in the first stripe.
+        let child = group.children()[0].as_ref();
+        assert_eq!(pattern.color_at_obj(child, &Tuple::point(1.5, 0.0, 0.0)), WHITE);
+        assert_eq!(pattern.color_at_obj(child, &Tuple::point(2.5, 0.0, 0.0)), BLACK);
+    }
+
+    #[test]
+    fn a_group_has_a_bounding_box_that_contains_its_children() {
+        let mut sphere = Sphere::new(2);
+        sphere.set_transformation(Matrix4::identity().translate(2.0, 5.0, -3.0).scale(2.0, 2.0, 2.0));
+        let mut cylinder = Cylinder::new(3);
+        cylinder.minimum = -2.0;
+        cylinder.maximum = 2.0;
+        cylinder.set_transformation(Matrix4::identity().translate(-4.0, -1.0, 4.0).scale(0.5, 1.0, 0.5));
+        let mut group = Group::new(1);
+        group.add_child(Box::new(sphere));
+        group.add_child(Box::new(cylinder));
+        group.set_transformation(Matrix4::identity().translate(10.0, 0.0, 0.0));
+
+        let bounds = group.bounds();
+        assert_eq!(bounds.min, Tuple::point(-4.5, -3.0, -5.0));
+        assert_eq!(bounds.max, Tuple::point(4.0, 7.0, 4.5));
+        let bounds = group.parent_space_bounds();
+        assert_eq!(bounds.min, Tuple::point(5.5, -3.0, -5.0));
+        assert!(Group::new(4).bounds().is_empty());
+    }
+
+    #[test]
+    fn intersecting_a_transformed_group_with_a_bvh() {
+        let mut group = Group::new(1);
+        for i in 0..10 {
+            let mut sphere = Sphere::new(2 + i);
+            sphere.set_transformation(Matrix4::identity().translate(i as f32 * 3.0, 0.0, 0.0));
+            group.add_child(Box::new(sphere));
+        }
+        group.build_bvh();
+        group.set_transformation(Matrix4::identity().translate(0.0, 5.0, 0.0).scale(2.0, 2.0, 2.0));
+
+        let ray = Ray::new(Tuple::point(18.0, 5.0, -10.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = group.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].obj.get_id(), 5);
+        assert_eq!(xs[0].t, 8.0);
+    }
+
+    #[test]
+    fn a_group_occludes_a_ray_only_within_the_distance() {
+        let mut group = Group::new(1);
+        let mut sphere = Sphere::new(2);
+        sphere.set_transformation(Matrix4::identity().translate(0.0, 0.0, 5.0));
+        group.add_child(Box::new(sphere));
+        group.add_child(Box::new(Sphere::new(3)));
+        group.set_transformation(Matrix4::identity().translate(10.0, 0.0, 0.0));
+        group.build_bvh();
+
+        // The first sphere is hit at t = 2, the second one is behind the ray.
+        let ray = Ray::new(Tuple::point(10.0, 0.0, 2.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(group.occludes(&ray, 10.0));
+        assert!(!group.occludes(&ray, 1.5));
+        let ray = Ray::new(Tuple::point(0.0, 0.0, 2.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(!group.occludes(&ray, 10.0));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::canvas::Canvas;
+use crate::Color;
+use std::fmt;
+use std::fs;
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(String),
+    Malformed(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(message) => write!(f, "Unable to read image file: {}", message),
+            ImageError::Malformed(message) => write!(f, "Malformed image: {}", message),
+        }
+    }
+}
+
+// Loads a high dynamic range image, picking the format from the file extension.
+pub fn load_hdr_image(file_name: &str) -> Result<Canvas, ImageError> {
+    let bytes = fs::read(file_name).map_err(|e| ImageError::Io(e.to_string()))?;
+    let lower_case = file_name.to_lowercase();
+    if lower_case.ends_with(".hdr") || lower_case.ends_with(".pic") {
+        parse_hdr(&bytes)
+    } else if lower_case.ends_with(".pfm") {
+        parse_pfm(&bytes)
+    } else {
+        Err(ImageError::Io(format!("{} is neither a .hdr nor a .pfm file", file_name)))
+    }
+}
+
+// Radiance RGBE. Supports flat and run length encoded scanlines in the usual -Y H +X W
+// orientation, but not the old run length encoding or the XYZE format.
+pub fn parse_hdr(bytes: &[u8]) -> Result<Canvas, ImageError> {
+    let mut reader = ByteReader { bytes, position: 0 };
+
+    let magic = reader.line()?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err(malformed("missing #?RADIANCE header"));
+    }
+    loop {
+        let line = reader.line()?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(malformed(&format!("unsupported {}", line)));
+        }
+    }
+
+    let resolution = reader.line()?;
+    let tokens: Vec<&str> = resolution.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+        return Err(malformed(&format!("unsupported resolution line {}", resolution)));
+    }
+    let height = parse_size(tokens[1])?;
+    let width = parse_size(tokens[3])?;
+    // Run length encoded scanlines take the least space, with runs of 127 pixels.
+    let smallest_scanline = if can_be_run_length_encoded(width) {
+        Some(4 + 8 * width.div_ceil(127))
+    } else {
+        width.checked_mul(4)
+    };
+    check_size(&reader, width, height, smallest_scanline)?;
+
+    let mut canvas = Canvas::new(width, height);
+    let mut scanline = vec![[0_u8; 4]; width];
+    for y in 0..height {
+        read_hdr_scanline(&mut reader, &mut scanline)?;
+        for (x, rgbe) in scanline.iter().enumerate() {
+            canvas.set_pixel(x, y, rgbe_to_color(rgbe));
+        }
+    }
+    Ok(canvas)
+}
+
+fn read_hdr_scanline(reader: &mut ByteReader, scanline: &mut [[u8; 4]]) -> Result<(), ImageError> {
+    let width = scanline.len();
+    let start = reader.bytes(4)?;
+    let is_run_length_encoded = can_be_run_length_encoded(width)
+        && start[0] == 2
+        && start[1] == 2
+        && ((start[2] as usize) << 8 | start[3] as usize) == width;
+
+    if !is_run_length_encoded {
+        scanline[0].copy_from_slice(start);
+        for pixel in scanline.iter_mut().skip(1) {
+            pixel.copy_from_slice(reader.bytes(4)?);
+        }
+        return Ok(());
+    }
+
+    // Each channel is stored on its own, as runs of a repeated byte or literal bytes.
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = reader.bytes(1)?[0] as usize;
+            if count > 128 {
+                let count = count - 128;
+                let value = reader.bytes(1)?[0];
+                if x + count > width {
+                    return Err(malformed("run goes past the end of the scanline"));
+                }
+                for pixel in scanline[x..x + count].iter_mut() {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(malformed("bad run length in scanline"));
+                }
+                let values = reader.bytes(count)?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = *value;
+                }
+                x += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn can_be_run_length_encoded(width: usize) -> bool {
+    (8..0x8000).contains(&width)
+}
+
+fn rgbe_to_color(rgbe: &[u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let factor = 2_f32.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f32 + 0.5) * factor,
+        (rgbe[1] as f32 + 0.5) * factor,
+        (rgbe[2] as f32 + 0.5) * factor,
+    )
+}
+
+// Portable float map. "PF" is RGB and "Pf" grayscale, and a negative scale means little
+// endian. Rows are stored from the bottom up.
+pub fn parse_pfm(bytes: &[u8]) -> Result<Canvas, ImageError> {
+    let mut reader = ByteReader { bytes, position: 0 };
+    let channels = match reader.token()?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(malformed("missing PF or Pf header")),
+    };
+    let width = parse_size(&reader.token()?)?;
+    let height = parse_size(&reader.token()?)?;
+    let scale: f32 = reader
+        .token()?
+        .parse()
+        .map_err(|_| malformed("the scale is not a number"))?;
+    // A single whitespace character separates the header from the data.
+    reader.bytes(1)?;
+    check_size(&reader, width, height, width.checked_mul(4 * channels))?;
+
+    let mut canvas = Canvas::new(width, height);
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let mut values = [0_f32; 3];
+            for value in values.iter_mut().take(channels) {
+                let mut raw = [0_u8; 4];
+                raw.copy_from_slice(reader.bytes(4)?);
+                *value = if scale < 0.0 {
+                    f32::from_le_bytes(raw)
+                } else {
+                    f32::from_be_bytes(raw)
+                };
+            }
+            if channels == 1 {
+                values = [values[0]; 3];
+            }
+            canvas.set_pixel(x, y, Color::new(values[0], values[1], values[2]));
+        }
+    }
+    Ok(canvas)
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], ImageError> {
+        if self.position + count > self.bytes.len() {
+            return Err(malformed("unexpected end of file"));
+        }
+        let result = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(result)
+    }
+
+    fn line(&mut self) -> Result<String, ImageError> {
+        let start = self.position;
+        while self.bytes(1)?[0] != b'\n' {}
+        Ok(String::from_utf8_lossy(&self.bytes[start..self.position - 1]).to_string())
+    }
+
+    // Skips leading whitespace, and stops before the whitespace following the token.
+    fn token(&mut self) -> Result<String, ImageError> {
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        let start = self.position;
+        while self.position < self.bytes.len() && !self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(malformed("unexpected end of header"));
+        }
+        Ok(String::from_utf8_lossy(&self.bytes[start..self.position]).to_string())
+    }
+}
+
+fn parse_size(token: &str) -> Result<usize, ImageError> {
+    match token.parse::<usize>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(malformed(&format!("bad image size {}", token))),
+    }
+}
+
+// Checked before allocating, so that a corrupt header gives an error rather than a canvas
+// too large for memory. row_size is the fewest bytes a row can take, if it fits in a usize.
+fn check_size(reader: &ByteReader, width: usize, height: usize, row_size: Option<usize>) -> Result<(), ImageError> {
+    let data_size = row_size.and_then(|row_size| row_size.checked_mul(height));
+    match (width.checked_mul(height), data_size) {
+        (Some(_), Some(data_size)) if data_size <= reader.remaining() => Ok(()),
+        _ => Err(malformed("image larger than the file")),
+    }
+}
+
+fn malformed(message: &str) -> ImageError {
+    ImageError::Malformed(String::from(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image_file::{parse_hdr, parse_pfm};
+    use crate::Color;
+
+    #[test]
+    fn parsing_a_flat_hdr_image() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n-Y 2 +X 2\n".to_vec();
+        bytes.extend_from_slice(&[128, 64, 0, 129]);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(&[128, 128, 128, 128]);
+        bytes.extend_from_slice(&[255, 0, 0, 131]);
+
+        let canvas = parse_hdr(&bytes).unwrap();
+        assert_eq!(canvas.width, 2);
+        assert_eq!(canvas.height, 2);
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(1.00391, 0.50391, 0.00391));
+        assert_eq!(canvas.pixel_at(1, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(canvas.pixel_at(0, 1), Color::new(0.50195, 0.50195, 0.50195));
+        assert_eq!(canvas.pixel_at(1, 1), Color::new(7.98438, 0.01563, 0.01563));
+    }
+
+    #[test]
+    fn parsing_a_run_length_encoded_hdr_image() {
+        let mut bytes = b"#?RGBE\n\n-Y 1 +X 10\n".to_vec();
+        bytes.extend_from_slice(&[2, 2, 0, 10]);
+        // Red: a run of 10. Green: 2 literals and a run of 8. Blue: 10 literals. Exponent: a run.
+        bytes.extend_from_slice(&[138, 128]);
+        bytes.extend_from_slice(&[2, 0, 64, 136, 255]);
+        bytes.extend_from_slice(&[10, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        bytes.extend_from_slice(&[138, 129]);
+
+        let canvas = parse_hdr(&bytes).unwrap();
+        assert_eq!(canvas.width, 10);
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(1.00391, 0.00391, 0.00391));
+        assert_eq!(canvas.pixel_at(1, 0), Color::new(1.00391, 0.50391, 0.01172));
+        assert_eq!(canvas.pixel_at(9, 0), Color::new(1.00391, 1.99609, 0.07422));
+    }
+
+    #[test]
+    fn malformed_hdr_images_give_errors() {
+        assert!(parse_hdr(b"P3\n1 1\n255\n").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 2 +X 1\n\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn images_larger_than_their_data_give_errors() {
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 4294967296 +X 4294967297\n\0\0\0\0").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 100000 +X 100000\n\0\0\0\0").is_err());
+        assert!(parse_hdr(b"#?RADIANCE\n\n-Y 1 +X 18446744073709551615\n\0\0\0\0").is_err());
+        assert!(parse_pfm(b"PF 4294967296 4294967297 -1.0\n\0\0\0\0").is_err());
+        assert!(parse_pfm(b"PF 100000 100000 -1.0\n\0\0\0\0").is_err());
+        assert!(parse_pfm(b"Pf 18446744073709551615 1 -1.0\n\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn parsing_a_pfm_image() {
+        // Little endian, with the bottom row first.
+        let mut bytes = b"PF\n2 2\n-1.0\n".to_vec();
+        let values = [0.0_f32, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 4.5, 4.5, 4.5];
+        for value in values.iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let canvas = parse_pfm(&bytes).unwrap();
+        assert_eq!(canvas.pixel_at(0, 1), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(canvas.pixel_at(1, 1), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(canvas.pixel_at(1, 0), Color::new(4.5, 4.5, 4.5));
+    }
+
+    #[test]
+    fn parsing_a_big_endian_grayscale_pfm_image() {
+        let mut bytes = b"Pf 2 1 1.0\n".to_vec();
+        bytes.extend_from_slice(&0.25_f32.to_be_bytes());
+        bytes.extend_from_slice(&2.0_f32.to_be_bytes());
+
+        let canvas = parse_pfm(&bytes).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(canvas.pixel_at(1, 0), Color::new(2.0, 2.0, 2.0));
+        assert!(parse_pfm(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
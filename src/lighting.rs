@@ -1,12 +1,96 @@
 use crate::color::Color;
 use crate::material::Material;
+use crate::math::INFINITY;
+use crate::random::Rng;
 use crate::tuple::{reflect, Tuple};
 use crate::Shape;
 use crate::BLACK;
 
+// The direction from the shaded point towards a point on the light, and how far away the
+// light is in that direction. Shadow rays are only tested up to the distance.
+pub struct LightSample {
+    pub direction: Tuple,
+    pub distance: f32,
+}
+
+impl LightSample {
+    pub fn towards(point: &Tuple, light_position: &Tuple) -> LightSample {
+        let vector = *light_position - *point;
+        LightSample {
+            direction: vector.normalize(),
+            distance: vector.mag(),
+        }
+    }
+}
+
+pub trait Light: Sync + Send {
+    fn intensity(&self) -> Color;
+    // The samples of the light used when shading point. Both lighting and the shadow
+    // test use them, so they must be the same every time for the same point.
+    fn samples(&self, point: &Tuple) -> Vec<LightSample>;
+    // How much of the light reaches point, ignoring shadows. Spot lights fade out towards
+    // the edge of their cone, and point and spot lights can be attenuated with distance.
+    fn falloff(&self, _point: &Tuple) -> f32 {
+        1.0
+    }
+}
+
+// How the light gets weaker with distance. With Polynomial, a constant of 1 and the
+// other terms 0 is the same as None.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Attenuation {
+    None,
+    // 1 / (constant + linear * d + quadratic * d^2)
+    Polynomial { constant: f32, linear: f32, quadratic: f32 },
+    // Physical inverse square falloff, so the intensity is what is received at distance 1.
+    // It is windowed to reach zero at range, beyond which the light has no effect at all.
+    InverseSquare { range: f32 },
+}
+
+impl Attenuation {
+    pub fn factor(&self, distance: f32) -> f32 {
+        match *self {
+            Attenuation::None => 1.0,
+            Attenuation::Polynomial { constant, linear, quadratic } => {
+                debug_assert!(self.is_valid(), "{:?} is not positive at every distance", self);
+                // Without a constant term, avoid dividing by zero on the light itself.
+                let distance = if constant == 0.0 { f32::max(distance, 0.01) } else { distance };
+                1.0 / (constant + linear * distance + quadratic * distance * distance)
+            }
+            Attenuation::InverseSquare { range } => {
+                if distance >= range {
+                    return 0.0;
+                }
+                let window = 1.0 - (distance / range).powi(4);
+                // Avoid dividing by zero on the light itself.
+                window * window / f32::max(distance * distance, 0.0001)
+            }
+        }
+    }
+
+    // A polynomial must be positive at every distance above 0, or the light would be
+    // infinitely bright or negative somewhere.
+    fn is_valid(&self) -> bool {
+        match *self {
+            Attenuation::Polynomial { constant, linear, quadratic } => {
+                if constant < 0.0 || quadratic < 0.0 {
+                    return false;
+                }
+                if quadratic == 0.0 {
+                    return linear > 0.0 || (linear == 0.0 && constant > 0.0);
+                }
+                // The lowest point is at -linear / (2 * quadratic), if that is above 0.
+                linear >= 0.0 || constant - linear * linear / (4.0 * quadratic) > 0.0
+            }
+            Attenuation::None | Attenuation::InverseSquare { .. } => true,
+        }
+    }
+}
+
 pub struct PointLight {
     pub position: Tuple,
     pub intensity: Color,
+    pub attenuation: Attenuation,
 }
 
 impl PointLight {
@@ -14,51 +98,237 @@ impl PointLight {
         PointLight {
             position,
             intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: &Tuple) -> Vec<LightSample> {
+        vec![LightSample::towards(point, &self.position)]
+    }
+
+    fn falloff(&self, point: &Tuple) -> f32 {
+        self.attenuation.factor((*point - self.position).mag())
+    }
+}
+
+// Lights a cone around direction. Within inner_angle of it the light is at full
+// intensity, and it fades smoothly to nothing at outer_angle. Both are half angles.
+pub struct SpotLight {
+    pub position: Tuple,
+    pub direction: Tuple,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl SpotLight {
+    pub fn new(position: Tuple, direction: Tuple, inner_angle: f32, outer_angle: f32, intensity: Color) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            inner_angle,
+            outer_angle,
+            intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: &Tuple) -> Vec<LightSample> {
+        vec![LightSample::towards(point, &self.position)]
+    }
+
+    fn falloff(&self, point: &Tuple) -> f32 {
+        let vector = *point - self.position;
+        let attenuation = self.attenuation.factor(vector.mag());
+        // There is no direction to compare with the cone on the light itself.
+        if vector.mag() == 0.0 {
+            return attenuation;
+        }
+        let cos_angle = vector.normalize().dot(&self.direction);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos_angle >= cos_inner {
+            return attenuation;
+        }
+        if cos_angle <= cos_outer {
+            return 0.0;
+        }
+        // Smoothstep, so there is no visible edge where the falloff starts.
+        let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t) * attenuation
+    }
+}
+
+// A rectangle spanned by two vectors from a corner, divided into usteps x vsteps cells.
+// Every cell is sampled once, at a random position within it, which gives soft shadows
+// with noise instead of banding.
+pub struct AreaLight {
+    pub corner: Tuple,
+    // The size of one cell.
+    pub uvec: Tuple,
+    pub usteps: u32,
+    pub vvec: Tuple,
+    pub vsteps: u32,
+    pub intensity: Color,
+    // Without jitter every cell is sampled in its centre. Mostly useful for testing.
+    pub jitter: bool,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Tuple,
+        full_uvec: Tuple,
+        usteps: u32,
+        full_vvec: Tuple,
+        vsteps: u32,
+        intensity: Color,
+    ) -> AreaLight {
+        // At least one cell each way, as there would be nothing to sample otherwise.
+        let usteps = u32::max(usteps, 1);
+        let vsteps = u32::max(vsteps, 1);
+        AreaLight {
+            corner,
+            uvec: full_uvec * (1.0 / usteps as f32),
+            usteps,
+            vvec: full_vvec * (1.0 / vsteps as f32),
+            vsteps,
+            intensity,
+            jitter: true,
+        }
+    }
+
+    pub fn position(&self) -> Tuple {
+        self.corner + self.uvec * (self.usteps as f32 / 2.0) + self.vvec * (self.vsteps as f32 / 2.0)
+    }
+
+    // Offsets are within the cell, from 0 to 1.
+    pub fn point_on_light(&self, u: u32, v: u32, u_offset: f32, v_offset: f32) -> Tuple {
+        self.corner + self.uvec * (u as f32 + u_offset) + self.vvec * (v as f32 + v_offset)
+    }
+
+    // One position in every cell, jittered with a seed from point.
+    pub fn sample_positions(&self, point: &Tuple) -> Vec<Tuple> {
+        let mut rng = Rng::from_floats(&[point.x, point.y, point.z]);
+        let mut positions = Vec::with_capacity((self.usteps * self.vsteps) as usize);
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let position = if self.jitter {
+                    self.point_on_light(u, v, rng.next_f32(), rng.next_f32())
+                } else {
+                    self.point_on_light(u, v, 0.5, 0.5)
+                };
+                positions.push(position);
+            }
+        }
+        positions
+    }
+}
+
+impl Light for AreaLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, point: &Tuple) -> Vec<LightSample> {
+        self.sample_positions(point)
+            .iter()
+            .map(|position| LightSample::towards(point, position))
+            .collect()
+    }
+}
+
+// A light infinitely far away, like the sun. Every point is lit from the same direction,
+// and shadow rays go on forever.
+pub struct DirectionalLight {
+    // The direction the light travels in, i.e. from the light towards the scene.
+    pub direction: Tuple,
+    pub intensity: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Tuple, intensity: Color) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalize(),
+            intensity,
         }
     }
 }
 
+impl Light for DirectionalLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, _: &Tuple) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: -self.direction,
+            distance: INFINITY,
+        }]
+    }
+}
+
+// light_intensity is the fraction of the light that is visible from point, from 0 when
+// it is completely in shadow to 1. Diffuse and specular are averaged over the samples.
 pub fn lighting(
     material: &Material,
     obj: &dyn Shape,
-    light: &PointLight,
+    light: &dyn Light,
     point: &Tuple,
     eye_dir: &Tuple,
     &surface_normal: &Tuple,
-    in_shadow: bool,
+    light_intensity: f32,
 ) -> Color {
     let color = match &material.pattern {
         Some(p) => p.color_at_obj(obj, point),
         None => material.color,
     };
 
-    let effective_color = color * light.intensity;
-
-    let light_vector = (light.position - *point).normalize();
+    let effective_color = color * light.intensity();
 
     let ambient = effective_color * material.ambient;
 
-    let light_dot_normal = light_vector.dot(&surface_normal);
+    if light_intensity <= 0_f32 {
+        return ambient;
+    }
+
+    let falloff = light.falloff(point);
+    if falloff <= 0_f32 {
+        return ambient;
+    }
 
-    let diffuse: Color;
-    let specular: Color;
-    if light_dot_normal < 0_f32 || in_shadow {
-        diffuse = BLACK;
-        specular = BLACK;
-    } else {
-        diffuse = effective_color * material.diffuse * light_dot_normal;
+    let samples = light.samples(point);
+    let mut sum = BLACK;
+    for sample in samples.iter() {
+        let light_vector = sample.direction;
+        let light_dot_normal = light_vector.dot(&surface_normal);
+        if light_dot_normal < 0_f32 {
+            continue;
+        }
+
+        sum = sum + effective_color * material.diffuse * light_dot_normal;
         let reflect_v = reflect(&(-light_vector), &surface_normal);
         let reflect_dot_eye = reflect_v.dot(&eye_dir);
 
-        if reflect_dot_eye <= 0_f32 {
-            specular = BLACK;
-        } else {
+        if reflect_dot_eye > 0_f32 {
             let factor = reflect_dot_eye.powf(material.shininess);
-            specular = light.intensity * material.specular * factor;
+            sum = sum + light.intensity() * material.specular * factor;
         }
     }
 
-    ambient + diffuse + specular
+    ambient + sum * (light_intensity * falloff / samples.len() as f32)
 }
 
 #[cfg(test)]
@@ -68,7 +338,9 @@ mod lighting_tests {
     use crate::material::Material;
     use crate::tuple::Tuple;
     use crate::sphere::Sphere;
-    use crate::lighting::{PointLight, lighting};
+    use crate::lighting::{AreaLight, Attenuation, DirectionalLight, Light, PointLight, SpotLight, lighting};
+    use crate::math::{INFINITY, PI};
+    use core::f32::consts::FRAC_1_SQRT_2;
 
     #[test]
     fn lighting_with_the_eye_between_the_light_and_the_surface() {
@@ -76,10 +348,7 @@ mod lighting_tests {
         let position = Tuple::point(0_f32, 0_f32, 0_f32);
         let eye_vector = Tuple::vector(0_f32, 0_f32, -1_f32);
         let normal = Tuple::vector(0_f32, 0_f32, -1_f32);
-        let light = PointLight {
-            position: Tuple::point(0_f32, 0_f32, -10_f32),
-            intensity: Color::new(1_f32, 1_f32, 1_f32),
-        };
+        let light = PointLight::new(Tuple::point(0_f32, 0_f32, -10_f32), Color::new(1_f32, 1_f32, 1_f32));
 
         let result = lighting(
             &material,
@@ -88,7 +357,7 @@ mod lighting_tests {
             &position,
             &eye_vector,
             &normal,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(1.9_f32, 1.9_f32, 1.9_f32));
     }
@@ -99,10 +368,7 @@ mod lighting_tests {
 
         let eye_vector = Tuple::vector(0_f32, 2_f32.sqrt() / 2_f32, -2_f32.sqrt() / 2_f32);
         let normal = Tuple::vector(0_f32, 0_f32, -1_f32);
-        let light = PointLight {
-            position: Tuple::point(0_f32, 0_f32, -10_f32),
-            intensity: Color::new(1.0_f32, 1.0_f32, 1.0_f32),
-        };
+        let light = PointLight::new(Tuple::point(0_f32, 0_f32, -10_f32), Color::new(1.0_f32, 1.0_f32, 1.0_f32));
 
         let result = lighting(
             &material,
@@ -111,7 +377,7 @@ mod lighting_tests {
             &position,
             &eye_vector,
             &normal,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(1_f32, 1_f32, 1_f32));
     }
@@ -121,10 +387,7 @@ mod lighting_tests {
         let position = Tuple::point(0_f32, 0_f32, 0_f32);
         let eye_vector = Tuple::vector(0_f32, 0_f32, -1_f32);
         let normal = Tuple::vector(0_f32, 0_f32, -1_f32);
-        let light = PointLight {
-            position: Tuple::point(0_f32, 10_f32, -10_f32),
-            intensity: Color::new(1.0_f32, 1.0_f32, 1.0_f32),
-        };
+        let light = PointLight::new(Tuple::point(0_f32, 10_f32, -10_f32), Color::new(1.0_f32, 1.0_f32, 1.0_f32));
 
         let result = lighting(
             &material,
@@ -133,7 +396,7 @@ mod lighting_tests {
             &position,
             &eye_vector,
             &normal,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(0.7364_f32, 0.7364_f32, 0.7364_f32));
     }
@@ -144,10 +407,7 @@ mod lighting_tests {
 
         let eye_vector = Tuple::vector(0_f32, -(2_f32.sqrt() / 2_f32), -2_f32.sqrt() / 2_f32);
         let normal = Tuple::vector(0_f32, 0_f32, -1_f32);
-        let light = PointLight {
-            position: Tuple::point(0_f32, 10_f32, -10_f32),
-            intensity: Color::new(1.0_f32, 1.0_f32, 1.0_f32),
-        };
+        let light = PointLight::new(Tuple::point(0_f32, 10_f32, -10_f32), Color::new(1.0_f32, 1.0_f32, 1.0_f32));
 
         let result = lighting(
             &material,
@@ -156,7 +416,7 @@ mod lighting_tests {
             &position,
             &eye_vector,
             &normal,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(1.63638_f32, 1.63638_f32, 1.63638_f32));
     }
@@ -166,10 +426,7 @@ mod lighting_tests {
         let position = Tuple::point(0_f32, 0_f32, 0_f32);
         let eye_vector = Tuple::vector(0_f32, 0_f32, -1_f32);
         let normal = Tuple::vector(0_f32, 0_f32, -1_f32);
-        let light = PointLight {
-            position: Tuple::point(0_f32, 0_f32, 10_f32),
-            intensity: Color::new(1_f32, 1_f32, 1_f32),
-        };
+        let light = PointLight::new(Tuple::point(0_f32, 0_f32, 10_f32), Color::new(1_f32, 1_f32, 1_f32));
 
         let result = lighting(
             &material,
@@ -178,7 +435,7 @@ mod lighting_tests {
             &position,
             &eye_vector,
             &normal,
-            false,
+            1.0,
         );
         assert_eq!(result, Color::new(0.1_f32, 0.1_f32, 0.1_f32));
     }
@@ -188,11 +445,8 @@ mod lighting_tests {
         let position = Tuple::point(0_f32, 0_f32, 0_f32);
         let eye_vector = Tuple::vector(0_f32, 0_f32, -1_f32);
         let normal = Tuple::vector(0_f32, 0_f32, -1_f32);
-        let light = PointLight {
-            position: Tuple::point(0_f32, 0_f32, -10_f32),
-            intensity: Color::new(1_f32, 1_f32, 1_f32),
-        };
-        let in_shadow = true;
+        let light = PointLight::new(Tuple::point(0_f32, 0_f32, -10_f32), Color::new(1_f32, 1_f32, 1_f32));
+        let light_intensity = 0.0;
 
         let result = lighting(
             &material,
@@ -201,8 +455,264 @@ mod lighting_tests {
             &position,
             &eye_vector,
             &normal,
-            in_shadow,
+            light_intensity,
         );
         assert_eq!(result, Color::new(0.1_f32, 0.1_f32, 0.1_f32));
     }
+    #[test]
+    fn lighting_uses_the_light_intensity_to_attenuate_color() {
+        let mut material = Material::default();
+        material.ambient = 0.1;
+        material.diffuse = 0.9;
+        material.specular = 0.0;
+        material.color = Color::new(1.0, 1.0, 1.0);
+        let position = Tuple::point(0.0, 0.0, -1.0);
+        let eye_vector = Tuple::vector(0.0, 0.0, -1.0);
+        let normal = Tuple::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+
+        let test_cases = vec![(1.0, 1.0), (0.5, 0.55), (0.0, 0.1)];
+        for test_case in test_cases {
+            let result = lighting(&material, &Sphere::new(0), &light, &position, &eye_vector, &normal, test_case.0);
+            assert_eq!(result, Color::new(test_case.1, test_case.1, test_case.1), "Intensity: {}", test_case.0);
+        }
+    }
+    #[test]
+    fn creating_an_area_light() {
+        let light = AreaLight::new(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            4,
+            Tuple::vector(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(light.uvec, Tuple::vector(0.5, 0.0, 0.0));
+        assert_eq!(light.vvec, Tuple::vector(0.0, 0.0, 0.5));
+        assert_eq!(light.position(), Tuple::point(1.0, 0.0, 0.5));
+        assert_eq!(light.sample_positions(&Tuple::point(0.0, 5.0, 0.0)).len(), 8);
+    }
+    #[test]
+    fn an_area_light_has_at_least_one_cell_each_way() {
+        let light = AreaLight::new(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            0,
+            Tuple::vector(0.0, 0.0, 1.0),
+            0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!((light.usteps, light.vsteps), (1, 1));
+        assert_eq!(light.uvec, Tuple::vector(2.0, 0.0, 0.0));
+        assert_eq!(light.position(), Tuple::point(1.0, 0.0, 0.5));
+        assert_eq!(light.sample_positions(&Tuple::point(0.0, 5.0, 0.0)).len(), 1);
+    }
+    #[test]
+    fn finding_a_point_on_an_area_light() {
+        let light = AreaLight::new(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            4,
+            Tuple::vector(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let test_cases = vec![
+            (0, 0, Tuple::point(0.25, 0.0, 0.25)),
+            (1, 0, Tuple::point(0.75, 0.0, 0.25)),
+            (0, 1, Tuple::point(0.25, 0.0, 0.75)),
+            (2, 0, Tuple::point(1.25, 0.0, 0.25)),
+            (3, 1, Tuple::point(1.75, 0.0, 0.75)),
+        ];
+        for test_case in test_cases {
+            let point = light.point_on_light(test_case.0, test_case.1, 0.5, 0.5);
+            assert_eq!(point, test_case.2, "Cell: {}, {}", test_case.0, test_case.1);
+        }
+    }
+    #[test]
+    fn jittered_samples_stay_within_their_cells() {
+        let light = AreaLight::new(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            4,
+            Tuple::vector(0.0, 0.0, 1.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let point = Tuple::point(1.0, 3.0, 2.0);
+        let samples = light.sample_positions(&point);
+        assert!(samples == light.sample_positions(&point));
+        for (i, sample) in samples.iter().enumerate() {
+            let u = (i % 4) as f32 * 0.5;
+            let v = (i / 4) as f32 * 0.5;
+            assert!(sample.x >= u && sample.x <= u + 0.5, "Sample: {}", sample);
+            assert!(sample.z >= v && sample.z <= v + 0.5, "Sample: {}", sample);
+        }
+    }
+    #[test]
+    fn lighting_samples_the_area_light() {
+        let light = AreaLight {
+            jitter: false,
+            ..AreaLight::new(
+                Tuple::point(-0.5, -0.5, -5.0),
+                Tuple::vector(1.0, 0.0, 0.0),
+                2,
+                Tuple::vector(0.0, 1.0, 0.0),
+                2,
+                Color::new(1.0, 1.0, 1.0),
+            )
+        };
+        let mut sphere = Sphere::new(0);
+        sphere.material.ambient = 0.1;
+        sphere.material.diffuse = 0.9;
+        sphere.material.specular = 0.0;
+        sphere.material.color = Color::new(1.0, 1.0, 1.0);
+        let eye = Tuple::point(0.0, 0.0, -5.0);
+
+        let test_cases = vec![
+            (Tuple::point(0.0, 0.0, -1.0), Color::new(0.9965, 0.9965, 0.9965)),
+            (Tuple::point(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2), Color::new(0.62318, 0.62318, 0.62318)),
+        ];
+        for test_case in test_cases {
+            let point = test_case.0;
+            let eye_vector = (eye - point).normalize();
+            let normal = Tuple::vector(point.x, point.y, point.z);
+            let result = lighting(&sphere.material, &sphere, &light, &point, &eye_vector, &normal, 1.0);
+            assert_eq!(result, test_case.1, "Point: {}", point);
+        }
+    }
+    #[test]
+    fn a_spot_light_fades_out_between_its_inner_and_outer_cone() {
+        let light = SpotLight::new(
+            Tuple::point(0.0, 10.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        let test_cases = vec![
+            (Tuple::point(0.0, 0.0, 0.0), 1.0),
+            (Tuple::point(3.0, 0.0, 0.0), 1.0),
+            (Tuple::point(10.0, 0.0, 0.0), 0.0),
+            (Tuple::point(0.0, 20.0, 0.0), 0.0),
+            (Tuple::point(0.0, 10.0, 0.0), 1.0),
+            // 30 degrees from the direction, between the cones.
+            (Tuple::point(0.0, 0.0, 10.0 * (PI / 6.0).tan()), 0.824),
+        ];
+        for test_case in test_cases {
+            let falloff = light.falloff(&test_case.0);
+            assert!((falloff - test_case.1).abs() < 0.01, "Point: {}, falloff: {}", test_case.0, falloff);
+        }
+    }
+    #[test]
+    fn lighting_outside_of_a_spot_light_cone_is_only_ambient() {
+        let material = Material::default();
+        let eye_vector = Tuple::vector(0.0, 1.0, 0.0);
+        let normal = Tuple::vector(0.0, 1.0, 0.0);
+        let light = SpotLight::new(
+            Tuple::point(0.0, 10.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+
+        let inside = lighting(&material, &Sphere::new(0), &light, &Tuple::point(0.0, 0.0, 0.0), &eye_vector, &normal, 1.0);
+        let outside = lighting(&material, &Sphere::new(0), &light, &Tuple::point(20.0, 0.0, 0.0), &eye_vector, &normal, 1.0);
+        assert_eq!(inside, Color::new(1.9, 1.9, 1.9));
+        assert_eq!(outside, Color::new(0.1, 0.1, 0.1));
+    }
+    #[test]
+    fn a_directional_light_shines_from_the_same_direction_everywhere() {
+        let light = DirectionalLight::new(Tuple::vector(0.0, -2.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        for point in [Tuple::point(0.0, 0.0, 0.0), Tuple::point(1000.0, -5.0, 200.0)].iter() {
+            let samples = light.samples(point);
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].direction, Tuple::vector(0.0, 1.0, 0.0));
+            assert_eq!(samples[0].distance, INFINITY);
+        }
+    }
+    #[test]
+    fn lighting_with_a_directional_light_is_the_same_across_a_plane() {
+        let material = Material::default();
+        let eye_vector = Tuple::vector(0.0, 1.0, 0.0);
+        let normal = Tuple::vector(0.0, 1.0, 0.0);
+        let light = DirectionalLight::new(Tuple::vector(-1.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+
+        let near = lighting(&material, &Sphere::new(0), &light, &Tuple::point(0.0, 0.0, 0.0), &eye_vector, &normal, 1.0);
+        let far = lighting(&material, &Sphere::new(0), &light, &Tuple::point(500.0, 0.0, -300.0), &eye_vector, &normal, 1.0);
+        assert_eq!(near, far);
+        let diffuse = 0.9 * 2_f32.sqrt() / 2.0;
+        assert_eq!(near, Color::new(0.1 + diffuse, 0.1 + diffuse, 0.1 + diffuse));
+    }
+    #[test]
+    fn attenuation_factors_at_different_distances() {
+        let polynomial = Attenuation::Polynomial { constant: 1.0, linear: 0.5, quadratic: 0.25 };
+        let quadratic = Attenuation::Polynomial { constant: 0.0, linear: 0.0, quadratic: 0.01 };
+        let linear = Attenuation::Polynomial { constant: 0.0, linear: 1.0, quadratic: 0.0 };
+        let inverse_square = Attenuation::InverseSquare { range: 10.0 };
+        let test_cases = vec![
+            (Attenuation::None, 100.0, 1.0),
+            (polynomial, 0.0, 1.0),
+            (polynomial, 2.0, 1.0 / 3.0),
+            (polynomial, 4.0, 1.0 / 7.0),
+            (quadratic, 10.0, 1.0),
+            (linear, 2.0, 0.5),
+            (linear, 0.0, 100.0),
+            (inverse_square, 1.0, 0.9998),
+            (inverse_square, 2.0, 0.2492),
+            (inverse_square, 5.0, 0.03516),
+            (inverse_square, 10.0, 0.0),
+            (inverse_square, 20.0, 0.0),
+        ];
+        for test_case in test_cases {
+            let factor = test_case.0.factor(test_case.1);
+            assert!((factor - test_case.2).abs() < 0.0001, "{:?} at {}: {}", test_case.0, test_case.1, factor);
+        }
+    }
+    #[test]
+    fn attenuation_polynomials_must_be_positive() {
+        let test_cases = vec![
+            ((1.0, 0.0, 0.0), true),
+            ((0.0, 0.0, 0.01), true),
+            ((0.0, 0.5, 0.0), true),
+            ((1.0, -0.1, 0.01), true),
+            ((0.0, 0.0, 0.0), false),
+            ((-1.0, 1.0, 1.0), false),
+            ((1.0, 0.0, -0.01), false),
+            ((1.0, -0.1, 0.0), false),
+            ((1.0, -1.0, 0.1), false),
+        ];
+        for ((constant, linear, quadratic), expected) in test_cases {
+            let attenuation = Attenuation::Polynomial { constant, linear, quadratic };
+            assert_eq!(attenuation.is_valid(), expected, "{:?}", attenuation);
+        }
+    }
+    #[test]
+    fn an_attenuated_light_lights_a_far_surface_less_than_a_near_one() {
+        let material = Material::default();
+        let eye_vector = Tuple::vector(0.0, 0.0, -1.0);
+        let normal = Tuple::vector(0.0, 0.0, -1.0);
+        let mut light = PointLight::new(Tuple::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        light.attenuation = Attenuation::Polynomial { constant: 0.0, linear: 0.0, quadratic: 0.01 };
+
+        let near = lighting(&material, &Sphere::new(0), &light, &Tuple::point(0.0, 0.0, 0.0), &eye_vector, &normal, 1.0);
+        let far = lighting(&material, &Sphere::new(0), &light, &Tuple::point(0.0, 0.0, 10.0), &eye_vector, &normal, 1.0);
+        // Ambient is not attenuated, diffuse and specular are divided by the squared distance.
+        assert_eq!(near, Color::new(1.9, 1.9, 1.9));
+        assert_eq!(far, Color::new(0.55, 0.55, 0.55));
+    }
+    #[test]
+    fn a_spot_light_combines_its_cone_with_attenuation() {
+        let mut light = SpotLight::new(
+            Tuple::point(0.0, 10.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        light.attenuation = Attenuation::InverseSquare { range: 15.0 };
+        assert!(light.falloff(&Tuple::point(0.0, 0.0, 0.0)) > light.falloff(&Tuple::point(0.0, -4.0, 0.0)));
+        assert_eq!(light.falloff(&Tuple::point(0.0, -6.0, 0.0)), 0.0);
+    }
 }
@@ -11,25 +11,42 @@ use crate::world_generator::generate_world;
 use crate::world_generator::generate_test_world;
 use core::f32::consts::PI;
 use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+mod adaptive;
+mod background;
+mod bounds;
+mod bvh;
 mod camera;
+mod canvas;
 mod color;
+mod cone;
+mod csg;
 mod cube;
+mod cylinder;
+mod environment;
+mod group;
+mod image_file;
 mod lighting;
 mod material;
 mod math;
 mod matrix;
+mod moving;
+mod obj_parser;
 mod pattern;
 mod plane;
+mod random;
 mod ray;
+mod sampling;
 mod shape;
+mod smooth_triangle;
 mod sphere;
+mod stereo;
 mod transformation;
+mod triangle;
 mod tuple;
 mod world;
 mod world_generator;
 
 use color::Color;
-use lighting::PointLight;
 use matrix::Matrix4;
 use ray::Ray;
 use tuple::Tuple;
@@ -39,7 +56,8 @@ const DIM_Y: usize = 1440;
 //const DIM_Y: usize = 540;
 
 fn main() {
-    let world = generate_world();
+    let mut world = generate_world();
+    world.build_bvh();
 
     let mut camera = Camera::new(DIM_X, DIM_Y, PI / 3_f32);
     let from = Tuple::point(25_f32, 20_f32, -60_f32);
@@ -19,6 +19,10 @@ pub struct Material {
 
     pub color: Color,
     pub pattern: Option<Box<dyn Pattern>>,
+
+    // Light given off by the surface itself, independent of lights and shadows.
+    pub emission: Color,
+    pub emission_strength: f32,
 }
 
 impl Material {
@@ -33,8 +37,30 @@ impl Material {
             transparency: 0_f32,
             color: Color::new(1_f32, 1_f32, 1_f32),
             pattern: None,
+            emission: Color::new(0_f32, 0_f32, 0_f32),
+            emission_strength: 1_f32,
+        }
+    }
+
+    // A surface that only gives off light, without being lit by anything.
+    pub fn emissive(emission: Color) -> Material {
+        Material {
+            ambient: 0_f32,
+            diffuse: 0_f32,
+            specular: 0_f32,
+            emission,
+            ..Material::default()
         }
     }
+
+    pub fn emitted(&self) -> Color {
+        self.emission * self.emission_strength
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        let emitted = self.emitted();
+        emitted.red > 0_f32 || emitted.green > 0_f32 || emitted.blue > 0_f32
+    }
 }
 
 #[cfg(test)]
@@ -45,7 +71,7 @@ mod material_tests {
     use crate::material::Material;
     use crate::pattern::StripePattern;
     use crate::Color;
-    use crate::PointLight;
+    use crate::lighting::PointLight;
     use crate::sphere::Sphere;
     use crate::Tuple;
 
@@ -69,7 +95,7 @@ mod material_tests {
             &Tuple::point(0.9_f32, 0_f32, 0_f32),
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         let c2 = lighting(
             &material,
@@ -78,7 +104,7 @@ mod material_tests {
             &Tuple::point(1.1_f32, 0_f32, 0_f32),
             &eyev,
             &normalv,
-            false,
+            1.0,
         );
         let color = match &material.pattern {
             Some(p) => p.color_at(&Tuple::point(0.9_f32, 0_f32, 0_f32)),
@@ -88,4 +114,26 @@ mod material_tests {
         assert_eq!(c2, BLACK);
         assert_eq!(color, WHITE);
     }
+
+    #[test]
+    fn a_material_emits_its_emission_scaled_by_the_strength() {
+        let mut material = Material::default();
+        assert!(!material.is_emissive());
+        assert_eq!(material.emitted(), BLACK);
+        material.emission = Color::new(1_f32, 0.5_f32, 0_f32);
+        material.emission_strength = 4_f32;
+        assert!(material.is_emissive());
+        assert_eq!(material.emitted(), Color::new(4_f32, 2_f32, 0_f32));
+    }
+
+    #[test]
+    fn an_emissive_material_is_not_lit() {
+        let material = Material::emissive(WHITE);
+        let light = PointLight::new(Tuple::point(0_f32, 0_f32, -10_f32), WHITE);
+        let eyev = Tuple::vector(0_f32, 0_f32, -1_f32);
+        let normalv = Tuple::vector(0_f32, 0_f32, -1_f32);
+        let point = Tuple::point(0_f32, 0_f32, 0_f32);
+        assert_eq!(lighting(&material, &Sphere::new(0), &light, &point, &eyev, &normalv, 1.0), BLACK);
+        assert_eq!(material.emitted(), WHITE);
+    }
 }
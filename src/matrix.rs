@@ -130,7 +130,7 @@ fn determinant3(m: &Matrix3) -> f32 {
     result
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Matrix4 {
     values: [f32; 16],
 }
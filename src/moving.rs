@@ -0,0 +1,226 @@
+use crate::bounds::BoundingBox;
+use crate::material::Material;
+use crate::matrix::{inverse4, Matrix4};
+use crate::ray::{transform, Intersection};
+use crate::shape::Shape;
+use crate::Ray;
+use crate::Tuple;
+
+// Moves a shape between two keyframes, for motion blur. At time 0 the shape has the start
+// transformation, at time 1 the end one, and in between every element of the matrix is
+// interpolated. That is exact for translation and scaling, while rotations should be kept
+// small between two keyframes. Times outside [0, 1] hold the closest keyframe.
+//
+// Unlike groups, the motion can't be baked into the child, so it is intersected in the
+// space of the moving shape, and the hits are reported on the moving shape itself. That
+// makes it meant for single primitives, and patterns follow the shape at time 0.
+pub struct Moving {
+    pub id: u32,
+    // Applied on top of the keyframes, like for any other shape.
+    transformation: Matrix4,
+    start: Matrix4,
+    end: Matrix4,
+    parent_transformation: Matrix4,
+    // From world space to the child's object space at time 0.
+    inverse_transformation: Matrix4,
+    child: Box<dyn Shape>,
+}
+
+impl Moving {
+    pub fn new(id: u32, mut child: Box<dyn Shape>, start: Matrix4, end: Matrix4) -> Moving {
+        child.set_parent_transformation(&Matrix4::identity());
+        let mut moving = Moving {
+            id,
+            transformation: Matrix4::identity(),
+            start,
+            end,
+            parent_transformation: Matrix4::identity(),
+            inverse_transformation: Matrix4::identity(),
+            child,
+        };
+        moving.update_transformations();
+        moving
+    }
+
+    pub fn child(&self) -> &dyn Shape {
+        self.child.as_ref()
+    }
+
+    // From the space of the child to world space.
+    pub fn transformation_at(&self, time: f32) -> Matrix4 {
+        let t = time.clamp(0.0, 1.0);
+        let mut keyframe = Matrix4::new_empty();
+        for row in 0..4 {
+            for column in 0..4 {
+                let index = [row, column];
+                keyframe[index] = self.start[index] * (1.0 - t) + self.end[index] * t;
+            }
+        }
+        &(&self.parent_transformation * &self.transformation) * &keyframe
+    }
+
+    fn update_transformations(&mut self) {
+        self.inverse_transformation =
+            self.child.get_inverse_transformation() * &inverse4(&self.transformation_at(0.0));
+    }
+
+    // Transforms the normal the child finds in its space back to world space.
+    fn normal_at_time(&self, world_point: &Tuple, time: f32, normal_at: impl Fn(&Tuple) -> Tuple) -> Tuple {
+        let inverse = inverse4(&self.transformation_at(time));
+        let local_normal = normal_at(&(&inverse * world_point));
+        let mut world_normal = &inverse.transpose() * &local_normal;
+        world_normal.w = 0.0;
+        world_normal.normalize()
+    }
+}
+
+impl Shape for Moving {
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.update_transformations();
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.parent_transformation = *parent;
+        self.update_transformations();
+    }
+
+    fn normal_at(&self, world_point: &Tuple) -> Tuple {
+        self.normal_at_time(world_point, 0.0, |point| self.child.normal_at(point))
+    }
+
+    fn normal_at_hit(&self, world_point: &Tuple, hit: &Intersection) -> Tuple {
+        self.normal_at_time(world_point, hit.time, |point| self.child.normal_at_hit(point, hit))
+    }
+
+    fn get_material(&self) -> &Material {
+        self.child.get_material()
+    }
+
+    fn get_mut_material(&mut self) -> &mut Material {
+        self.child.get_mut_material()
+    }
+
+    fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        let local_ray = transform(ray, &inverse4(&self.transformation_at(ray.time)));
+        self.child
+            .intersections_by(&local_ray)
+            .iter()
+            .map(|i| {
+                let mut hit = Intersection::new_with_uv(self, i.t, i.u, i.v);
+                hit.time = ray.time;
+                hit
+            })
+            .collect()
+    }
+
+    fn build_bvh(&mut self) {
+        self.child.build_bvh();
+    }
+
+    // Everywhere the shape is between the keyframes. Points move in a straight line from
+    // one keyframe to the other, so the bounds at both ends cover everything in between.
+    fn bounds(&self) -> BoundingBox {
+        let child_bounds = self.child.parent_space_bounds();
+        let mut bounds = child_bounds.transform(&self.start);
+        bounds.merge(&child_bounds.transform(&self.end));
+        bounds
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix4 {
+        &self.inverse_transformation
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::{render_pixel, Camera};
+    use crate::color::{BLACK, WHITE};
+    use crate::lighting::PointLight;
+    use crate::material::Material;
+    use crate::moving::Moving;
+    use crate::ray::prepare_computations;
+    use crate::sampling::{Filter, SamplePattern, Sampler};
+    use crate::shape::Shape;
+    use crate::sphere::Sphere;
+    use crate::world::World;
+    use crate::Matrix4;
+    use crate::Ray;
+    use crate::Tuple;
+    use core::f32::consts::PI;
+
+    fn moving_sphere() -> Moving {
+        Moving::new(
+            1,
+            Box::new(Sphere::new(2)),
+            Matrix4::identity(),
+            Matrix4::identity().translate(4.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn a_moving_shape_is_hit_where_it_is_at_the_time_of_the_ray() {
+        let sphere = moving_sphere();
+        let test_cases = vec![(0.0, 0.0, 2), (0.0, 4.0, 0), (0.5, 2.0, 2), (1.0, 4.0, 2), (1.0, 0.0, 0), (3.0, 4.0, 2)];
+        for (time, x, hits) in test_cases {
+            let ray = Ray::new_at_time(Tuple::point(x, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0), time);
+            let xs = sphere.intersections_by(&ray);
+            assert_eq!(xs.len(), hits, "Time: {}, x: {}", time, x);
+            if hits > 0 {
+                assert_eq!(xs[0].t, 4.0);
+                assert_eq!(xs[0].time, time);
+                assert_eq!(xs[0].obj.get_id(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn the_normal_follows_the_shape() {
+        let sphere = moving_sphere();
+        let ray = Ray::new_at_time(Tuple::point(-5.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0), 0.5);
+        let xs = sphere.intersections_by(&ray);
+        let comps = prepare_computations(&xs[0], &ray, &xs);
+        assert_eq!(comps.point, Tuple::point(1.0, 0.0, 0.0));
+        assert_eq!(comps.surface_normalv, Tuple::vector(-1.0, 0.0, 0.0));
+        assert_eq!(comps.time, 0.5);
+    }
+
+    #[test]
+    fn the_bounds_of_a_moving_shape_cover_both_keyframes() {
+        let mut sphere = moving_sphere();
+        sphere.set_transformation(Matrix4::identity().translate(0.0, 1.0, 0.0));
+        let bounds = sphere.parent_space_bounds();
+        assert_eq!(bounds.min, Tuple::point(-1.0, 0.0, -1.0));
+        assert_eq!(bounds.max, Tuple::point(5.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn a_moving_shape_is_blurred_while_the_shutter_is_open() {
+        // A white sphere moving across the view from the left to the right.
+        let mut world = World::new(PointLight::new(Tuple::point(0.0, 0.0, 10.0), BLACK));
+        let mut child = Sphere::new(2);
+        child.material = Material::emissive(WHITE);
+        world.add_object(Moving::new(
+            1,
+            Box::new(child),
+            Matrix4::identity().translate(2.0, 0.0, -10.0),
+            Matrix4::identity().translate(-2.0, 0.0, -10.0),
+        ));
+        let mut camera = Camera::new(11, 11, PI / 3_f32);
+        let sampler = Sampler::new(SamplePattern::Jittered, 8, Filter::Box);
+
+        // With the shutter closed, the sphere is only at the start.
+        assert_eq!(render_pixel(5, 5, &camera, &world, &sampler), BLACK);
+        camera.shutter_close = 1.0;
+        let center = render_pixel(5, 5, &camera, &world, &sampler);
+        assert!(center.red > 0.2 && center.red < 0.8, "{:?}", center);
+    }
+}
@@ -0,0 +1,401 @@
+use crate::group::Group;
+use crate::shape::Shape;
+use crate::smooth_triangle::SmoothTriangle;
+use crate::triangle::Triangle;
+use crate::Tuple;
+use std::fmt;
+use std::fs;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(String),
+    Malformed { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(message) => write!(f, "Unable to read obj file: {}", message),
+            ObjError::Malformed { line, message } => write!(f, "Line {}: {}", line, message),
+        }
+    }
+}
+
+pub struct ObjGroup {
+    pub name: String,
+    pub triangles: Vec<Box<dyn Shape>>,
+}
+
+pub struct ObjFile {
+    pub vertices: Vec<Tuple>,
+    pub normals: Vec<Tuple>,
+    // Faces that appear before the first "g" statement.
+    pub default_group: Vec<Box<dyn Shape>>,
+    pub groups: Vec<ObjGroup>,
+    // Lines with statements we don't support, e.g. "vt" or "usemtl".
+    pub ignored_lines: usize,
+    next_id: u32,
+}
+
+impl ObjFile {
+    // Every shape in the world needs an id, so triangles get consecutive ids starting at first_id.
+    pub fn new(first_id: u32) -> ObjFile {
+        ObjFile {
+            vertices: vec![],
+            normals: vec![],
+            default_group: vec![],
+            groups: vec![],
+            ignored_lines: 0,
+            next_id: first_id,
+        }
+    }
+
+    // The id the next shape created from this file (or by the caller) should use.
+    pub fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    // All triangles, ready to be pushed into World::objects.
+    pub fn into_shapes(self) -> Vec<Box<dyn Shape>> {
+        let mut shapes = self.default_group;
+        for group in self.groups {
+            shapes.extend(group.triangles);
+        }
+        shapes
+    }
+
+    // The whole file as one group, with a child group per named group. The groups
+    // get ids following the triangles.
+    pub fn into_group(self) -> Group {
+        let mut next_id = self.next_id;
+        let mut root = Group::new(next_id);
+        next_id += 1;
+        for triangle in self.default_group {
+            root.add_child(triangle);
+        }
+        for obj_group in self.groups {
+            let mut group = Group::new(next_id);
+            next_id += 1;
+            for triangle in obj_group.triangles {
+                group.add_child(triangle);
+            }
+            root.add_child(Box::new(group));
+        }
+        root
+    }
+
+    fn current_group(&mut self) -> &mut Vec<Box<dyn Shape>> {
+        match self.groups.last_mut() {
+            Some(group) => &mut group.triangles,
+            None => &mut self.default_group,
+        }
+    }
+
+    fn parse_line(&mut self, line: &str, line_number: usize) -> Result<(), ObjError> {
+        let mut tokens = line.split_whitespace();
+        let statement = match tokens.next() {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match statement {
+            "v" => {
+                let (x, y, z) = parse_xyz(&arguments, line_number)?;
+                self.vertices.push(Tuple::point(x, y, z));
+            }
+            "vn" => {
+                let (x, y, z) = parse_xyz(&arguments, line_number)?;
+                self.normals.push(Tuple::vector(x, y, z));
+            }
+            "f" => self.parse_face(&arguments, line_number)?,
+            "g" => {
+                if arguments.is_empty() {
+                    return Err(malformed(line_number, "group statement without a name"));
+                }
+                self.groups.push(ObjGroup {
+                    name: arguments.join(" "),
+                    triangles: vec![],
+                });
+            }
+            _ if statement.starts_with('#') => {}
+            _ => self.ignored_lines += 1,
+        }
+        Ok(())
+    }
+
+    // Polygons are fan triangulated around their first vertex. Normals are only
+    // used if every vertex of the face has one.
+    fn parse_face(&mut self, arguments: &[&str], line_number: usize) -> Result<(), ObjError> {
+        if arguments.len() < 3 {
+            return Err(malformed(line_number, "a face needs at least 3 vertices"));
+        }
+
+        let mut vertices = Vec::with_capacity(arguments.len());
+        let mut normals = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            // Each vertex is one of v, v/vt, v//vn or v/vt/vn.
+            let mut indices = argument.split('/');
+            let vertex = indices.next().unwrap_or("");
+            let _texture = indices.next();
+            let normal = indices.next();
+            if indices.next().is_some() {
+                return Err(malformed(line_number, &format!("invalid face vertex '{}'", argument)));
+            }
+
+            let vertex_index = resolve_index(vertex, self.vertices.len(), line_number)?;
+            vertices.push(self.vertices[vertex_index]);
+            match normal {
+                Some(n) if !n.is_empty() => {
+                    let normal_index = resolve_index(n, self.normals.len(), line_number)?;
+                    normals.push(self.normals[normal_index]);
+                }
+                _ => {}
+            }
+        }
+
+        let is_smooth = normals.len() == vertices.len();
+        for i in 1..vertices.len() - 1 {
+            let id = self.next_id;
+            self.next_id += 1;
+            let triangle: Box<dyn Shape> = if is_smooth {
+                Box::new(SmoothTriangle::new(
+                    id,
+                    vertices[0],
+                    vertices[i],
+                    vertices[i + 1],
+                    normals[0],
+                    normals[i],
+                    normals[i + 1],
+                ))
+            } else {
+                Box::new(Triangle::new(id, vertices[0], vertices[i], vertices[i + 1]))
+            };
+            self.current_group().push(triangle);
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_obj(content: &str, first_id: u32) -> Result<ObjFile, ObjError> {
+    let mut obj = ObjFile::new(first_id);
+    for (i, line) in content.lines().enumerate() {
+        obj.parse_line(line, i + 1)?;
+    }
+    Ok(obj)
+}
+
+pub fn parse_obj_file(file_name: &str, first_id: u32) -> Result<ObjFile, ObjError> {
+    let content = fs::read_to_string(file_name).map_err(|e| ObjError::Io(e.to_string()))?;
+    parse_obj(&content, first_id)
+}
+
+fn malformed(line: usize, message: &str) -> ObjError {
+    ObjError::Malformed {
+        line,
+        message: String::from(message),
+    }
+}
+
+fn parse_xyz(arguments: &[&str], line_number: usize) -> Result<(f32, f32, f32), ObjError> {
+    // A vertex can have an optional w component, which we ignore.
+    if arguments.len() < 3 || arguments.len() > 4 {
+        return Err(malformed(line_number, "expected 3 coordinates"));
+    }
+    let mut values = [0_f32; 3];
+    for (value, argument) in values.iter_mut().zip(arguments) {
+        *value = argument
+            .parse::<f32>()
+            .map_err(|_| malformed(line_number, &format!("invalid number '{}'", argument)))?;
+    }
+    Ok((values[0], values[1], values[2]))
+}
+
+// Indices are 1-based, and negative indices count backwards from the last element read so far.
+fn resolve_index(index: &str, count: usize, line_number: usize) -> Result<usize, ObjError> {
+    let value = index
+        .parse::<i64>()
+        .map_err(|_| malformed(line_number, &format!("invalid index '{}'", index)))?;
+    let resolved = if value < 0 { count as i64 + value } else { value - 1 };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(malformed(line_number, &format!("index {} is out of range", value)));
+    }
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::obj_parser::{parse_obj, ObjError};
+    use crate::ray::Ray;
+    use crate::Matrix4;
+    use crate::Shape;
+    use crate::tuple::Tuple;
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let content = "There was a young lady named Bright
+who traveled much faster than light.
+She set out one day
+in a relative way,
+and came back the previous night.";
+        let obj = parse_obj(content, 1).unwrap();
+        assert_eq!(obj.ignored_lines, 5);
+        assert_eq!(obj.vertices.len(), 0);
+    }
+
+    #[test]
+    fn vertex_records() {
+        let content = "v -1 1 0
+v -1.0000 0.5000 0.0000
+v 1 0 0
+v 1 1 0";
+        let obj = parse_obj(content, 1).unwrap();
+        assert_eq!(obj.vertices[0], Tuple::point(-1.0, 1.0, 0.0));
+        assert_eq!(obj.vertices[1], Tuple::point(-1.0, 0.5, 0.0));
+        assert_eq!(obj.vertices[2], Tuple::point(1.0, 0.0, 0.0));
+        assert_eq!(obj.vertices[3], Tuple::point(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let content = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+
+f 1 2 3
+f 1 3 4";
+        let obj = parse_obj(content, 10).unwrap();
+        assert_eq!(obj.default_group.len(), 2);
+        assert_eq!(obj.default_group[0].get_id(), 10);
+        assert_eq!(obj.default_group[1].get_id(), 11);
+        assert_eq!(obj.next_id(), 12);
+
+        let ray = Ray::new(Tuple::point(0.5, 0.9, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(obj.default_group[0].intersections_by(&ray).len(), 0);
+        assert_eq!(obj.default_group[1].intersections_by(&ray).len(), 1);
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let content = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+
+f 1 2 3 4 5";
+        let obj = parse_obj(content, 1).unwrap();
+        assert_eq!(obj.default_group.len(), 3);
+    }
+
+    #[test]
+    fn triangles_in_groups() {
+        let content = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4";
+        let obj = parse_obj(content, 1).unwrap();
+        assert_eq!(obj.default_group.len(), 0);
+        assert_eq!(obj.groups.len(), 2);
+        assert_eq!(obj.groups[0].name, "FirstGroup");
+        assert_eq!(obj.groups[0].triangles.len(), 1);
+        assert_eq!(obj.groups[1].name, "SecondGroup");
+        assert_eq!(obj.groups[1].triangles.len(), 1);
+        assert_eq!(obj.into_shapes().len(), 2);
+    }
+
+    #[test]
+    fn converting_an_obj_file_to_a_group() {
+        let content = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 4
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4";
+        let obj = parse_obj(content, 1).unwrap();
+        let mut group = obj.into_group();
+        assert_eq!(group.get_id(), 4);
+        assert_eq!(group.children().len(), 3);
+        assert_eq!(group.children()[1].get_id(), 5);
+        assert_eq!(group.children()[2].get_id(), 6);
+
+        group.set_transformation(Matrix4::identity().translate(0.0, 0.0, 1.0));
+        let ray = Ray::new(Tuple::point(0.5, 0.9, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = group.intersections_by(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 6.0);
+    }
+
+    #[test]
+    fn vertex_normal_records() {
+        let content = "vn 0 0 1
+vn 0.707 0 -0.707
+vn 1 2 3";
+        let obj = parse_obj(content, 1).unwrap();
+        assert_eq!(obj.normals[0], Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(obj.normals[1], Tuple::vector(0.707, 0.0, -0.707));
+        assert_eq!(obj.normals[2], Tuple::vector(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn faces_with_normals_become_smooth_triangles() {
+        let content = "v 0 1 0
+v -1 0 0
+v 1 0 0
+
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+
+f 1//3 2//1 3//2
+f 1/0/3 2/102/1 3/14/2
+f 1 2 3";
+        let obj = parse_obj(content, 1).unwrap();
+        assert_eq!(obj.default_group.len(), 3);
+
+        // The smooth triangles interpolate the normal, the flat one does not.
+        let point = Tuple::point(0.0, 0.5, 0.0);
+        let expected = Tuple::vector(0.0, 1.0, 0.0);
+        assert_eq!(obj.default_group[0].normal_at(&point), expected);
+        assert_eq!(obj.default_group[1].normal_at(&point), expected);
+        assert_eq!(obj.default_group[2].normal_at(&point), Tuple::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn negative_indices_are_relative_to_the_end() {
+        let content = "v 0 1 0
+v -1 0 0
+v 1 0 0
+f -3 -2 -1";
+        let obj = parse_obj(content, 1).unwrap();
+        assert_eq!(obj.default_group.len(), 1);
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_with_line_numbers() {
+        let test_cases = vec![
+            ("v 1 2 3\nv 1 two 3", 2),
+            ("v 1 2", 1),
+            ("v 1 2 3\nv 1 2 3\n\nf 1 2", 4),
+            ("v 1 2 3\nv 1 2 3\nv 1 2 3\nf 1 2 4", 4),
+            ("v 1 2 3\nv 1 2 3\nv 1 2 3\nf 1//1 2 3", 4),
+            ("v 1 2 3\nv 1 2 3\nv 1 2 3\nf 1 a 3", 4),
+            ("g", 1),
+        ];
+
+        for test_case in test_cases {
+            match parse_obj(test_case.0, 1) {
+                Err(ObjError::Malformed { line, .. }) => assert_eq!(line, test_case.1, "{}", test_case.0),
+                _ => panic!("Expected a malformed line error for: {}", test_case.0),
+            }
+        }
+    }
+}
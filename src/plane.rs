@@ -1,5 +1,6 @@
-use crate::math::EPSILON;
+use crate::math::{EPSILON, INFINITY};
 use crate::Shape;
+use crate::bounds::BoundingBox;
 use crate::material::Material;
 use crate::matrix::inverse4;
 use crate::ray::transform;
@@ -11,6 +12,7 @@ use crate::Tuple;
 pub struct Plane {
     pub id: u32,
     pub material: Material,
+    pub transformation: Matrix4,
     pub inverse_transformation: Matrix4,
 }
 
@@ -19,14 +21,20 @@ impl Plane {
         Plane {
             id,
             material: Material::default(),
+            transformation: Matrix4::identity(),
             inverse_transformation: Matrix4::identity()
         }
     }
 }
 
 impl Shape for Plane {    
-    fn set_transformation(&mut self, t: Matrix4) {        
-        self.inverse_transformation = inverse4(&t)
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.inverse_transformation = inverse4(&self.transformation)
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.inverse_transformation = inverse4(&(parent * &self.transformation))
     }
 
     fn normal_at(&self, _: &Tuple) -> Tuple {
@@ -51,14 +59,19 @@ impl Shape for Plane {
             return vec![];
         }
         
-        let i1 = Intersection {
-            obj: self,
-            t: -ray.origin.y / ray.direction.y
-        };
+        let i1 = Intersection::new(self, -ray.origin.y / ray.direction.y);
         
         vec![i1]
     }
 
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-INFINITY, 0.0, -INFINITY), Tuple::point(INFINITY, 0.0, INFINITY))
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
     fn get_inverse_transformation(&self) -> &Matrix4 {
         &self.inverse_transformation
     }
@@ -70,6 +83,9 @@ impl Shape for Plane {
 
 #[cfg(test)]
 mod tests {    
+    use crate::Matrix4;
+    use crate::math::INFINITY;
+    use crate::bounds::BoundingBox;
     use crate::plane::Plane;
     use crate::Shape;
     use crate::Tuple;
@@ -119,6 +135,15 @@ mod tests {
         let xs = plane.intersections_by(&ray);
         assert_eq!(xs.len(), 1);
         assert_eq!(xs[0].t, 1_f32);
-    }    
-}
+    }
 
+    #[test]
+    fn a_plane_has_a_bounding_box() {
+        let mut plane = Plane::new(1);
+        let bounds = plane.bounds();
+        assert_eq!(bounds.min, Tuple::point(-INFINITY, 0_f32, -INFINITY));
+        assert_eq!(bounds.max, Tuple::point(INFINITY, 0_f32, INFINITY));
+        plane.set_transformation(Matrix4::identity().translate(0_f32, 5_f32, 0_f32));
+        assert_eq!(plane.parent_space_bounds(), BoundingBox::infinite());
+    }
+}
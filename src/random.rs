@@ -0,0 +1,72 @@
+// Small xorshift random number generator. Rendering runs on several threads, and the same
+// scene must give the same image every time, so there is no global generator. Instead
+// callers seed one from what they are computing, e.g. the point being shaded.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // Splitmix64, so that similar seeds still give unrelated sequences. Xorshift gets
+        // stuck at zero, which splitmix only returns for one seed.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    pub fn from_floats(values: &[f32]) -> Rng {
+        let mut seed = 0_u64;
+        for value in values {
+            seed = seed.rotate_left(21) ^ value.to_bits() as u64;
+            seed = seed.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        }
+        Rng::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // In [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1_u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::random::Rng;
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        let mut a = Rng::from_floats(&[1.0, 2.0, 3.0]);
+        let mut b = Rng::from_floats(&[1.0, 2.0, 3.0]);
+        let mut c = Rng::from_floats(&[1.0, 2.0, 3.001]);
+        let xs: Vec<_> = (0..10).map(|_| a.next_u64()).collect();
+        let ys: Vec<_> = (0..10).map(|_| b.next_u64()).collect();
+        let zs: Vec<_> = (0..10).map(|_| c.next_u64()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn random_floats_are_evenly_spread_between_zero_and_one() {
+        let mut rng = Rng::new(0);
+        let mut buckets = [0; 10];
+        for _ in 0..10000 {
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value));
+            buckets[(value * 10.0) as usize] += 1;
+        }
+        for count in buckets.iter() {
+            assert!(*count > 900 && *count < 1100, "{:?}", buckets);
+        }
+    }
+}
@@ -8,11 +8,31 @@ use std::cmp::Ordering;
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    // When the ray is cast, for motion blur. Rays spawned at a hit keep the time.
+    pub time: f32,
 }
 
 pub struct Intersection<'a> {
     pub obj: &'a dyn Shape,
     pub t: f32,
+    // Barycentric coordinates of the hit, only set by triangles.
+    pub u: f32,
+    pub v: f32,
+    // The closed volume the ray enters or leaves at this hit, used to track refractive
+    // indices. It is the shape itself, except for children of a CSG shape.
+    pub container: &'a dyn Shape,
+    // Time of the ray, only set by moving shapes that need it to find the normal.
+    pub time: f32,
+}
+
+impl<'a> Intersection<'a> {
+    pub fn new(obj: &'a dyn Shape, t: f32) -> Intersection<'a> {
+        Intersection { obj, t, u: 0.0, v: 0.0, container: obj, time: 0.0 }
+    }
+
+    pub fn new_with_uv(obj: &'a dyn Shape, t: f32, u: f32, v: f32) -> Intersection<'a> {
+        Intersection { obj, t, u, v, container: obj, time: 0.0 }
+    }
 }
 
 impl<'a> PartialEq for Intersection<'a> {
@@ -42,7 +62,11 @@ impl<'a> Ord for Intersection<'a> {
 
 impl Ray {
     pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        Ray { origin, direction }
+        Ray { origin, direction, time: 0.0 }
+    }
+
+    pub fn new_at_time(origin: Tuple, direction: Tuple, time: f32) -> Ray {
+        Ray { origin, direction, time }
     }
 
     pub fn position(&self, t: f32) -> Tuple {
@@ -61,6 +85,7 @@ pub fn transform(ray: &Ray, transformation: &Matrix4) -> Ray {
     Ray {
         origin: transformation * &ray.origin,
         direction: transformation * &ray.direction,
+        time: ray.time,
     }
 }
 
@@ -76,6 +101,7 @@ pub struct Computation<'a> {
     pub reflectv: Tuple,
     pub n1: f32,
     pub n2: f32,
+    pub time: f32,
 }
 
 // TODO: Find better name for this.
@@ -84,7 +110,8 @@ pub fn prepare_computations<'a>(
     ray: &Ray,
     intersections: &Vec<Intersection<'a>>,
 ) -> Computation<'a> {
-    let mut containers: Vec<&'a dyn Shape> = vec![];
+    // The containers we are inside of, and the shape we entered them through.
+    let mut containers: Vec<(&'a dyn Shape, &'a dyn Shape)> = vec![];
 
     // TODO: Improve this algorithm
     let mut n1: f32 = 1.0;
@@ -94,22 +121,22 @@ pub fn prepare_computations<'a>(
             if containers.len() == 0 {
                 n1 = 1_f32;
             } else {
-                n1 = (*containers.last().unwrap())
+                n1 = containers.last().unwrap().1
                     .get_material()
                     .refractive_index;
             }
         }
-        let index = containers.iter().position(|&o| std::ptr::eq(i.obj, o));
+        let index = containers.iter().position(|&(c, _)| std::ptr::eq(i.container, c));
         if index.is_some() {
             containers.remove(index.unwrap());
         } else {
-            containers.push(i.obj);
+            containers.push((i.container, i.obj));
         }
         if std::ptr::eq(i, hit) {
             if containers.len() == 0 {
                 n2 = 1_f32;
             } else {
-                n2 = (*containers.last().unwrap())
+                n2 = containers.last().unwrap().1
                     .get_material()
                     .refractive_index;
             }
@@ -118,7 +145,7 @@ pub fn prepare_computations<'a>(
     }
     let point = ray.position(hit.t);
     let eye_direction = -ray.direction;
-    let mut surface_normalv = hit.obj.normal_at(&point);
+    let mut surface_normalv = hit.obj.normal_at_hit(&point, hit);
     let mut is_inside = false;
     if eye_direction.dot(&surface_normalv) < 0_f32 {
         is_inside = true;
@@ -140,6 +167,7 @@ pub fn prepare_computations<'a>(
         reflectv,
         n1,
         n2,
+        time: ray.time,
     }
 }
 
@@ -204,6 +232,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(2_f32, 3_f32, 4_f32),
             direction: Tuple::vector(1_f32, 0_f32, 0_f32),
+            time: 0.0,
         };
         assert_eq!(ray.position(0_f32), Tuple::point(2_f32, 3_f32, 4_f32));
         assert_eq!(ray.position(1_f32), Tuple::point(3_f32, 3_f32, 4_f32));
@@ -215,6 +244,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(0_f32, 0_f32, -5_f32),
             direction: Tuple::vector(0_f32, 0_f32, 1_f32),
+            time: 0.0,
         };
         let sphere = Sphere::new(1);
         let xs = sphere.intersections_by(&ray);
@@ -227,6 +257,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(0_f32, 1_f32, -5_f32),
             direction: Tuple::vector(0_f32, 0_f32, 1_f32),
+            time: 0.0,
         };
         let sphere = Sphere::new(1);
         let xs = sphere.intersections_by(&ray);
@@ -239,6 +270,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(0_f32, 2_f32, -5_f32),
             direction: Tuple::vector(0_f32, 0_f32, 1_f32),
+            time: 0.0,
         };
         let sphere = Sphere::new(1);
         let xs = sphere.intersections_by(&ray);
@@ -249,6 +281,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(0_f32, 0_f32, 0_f32),
             direction: Tuple::vector(0_f32, 0_f32, 1_f32),
+            time: 0.0,
         };
         let sphere = Sphere::new(1);
         let xs = sphere.intersections_by(&ray);
@@ -261,6 +294,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(0_f32, 0_f32, 5_f32),
             direction: Tuple::vector(0_f32, 0_f32, 1_f32),
+            time: 0.0,
         };
         let sphere = Sphere::new(1);
         let xs = sphere.intersections_by(&ray);
@@ -271,9 +305,9 @@ mod ray_tests {
     #[test]
     fn hit_when_all_intersections_have_positive_t() {
         let s = Sphere::new(1);
-        let i1 = Intersection { obj: &s, t: 1_f32 };
+        let i1 = Intersection::new(&s, 1_f32);
 
-        let i2 = Intersection { obj: &s, t: 2_f32 };
+        let i2 = Intersection::new(&s, 2_f32);
         let intersections = vec![i2, i1];
         let hit = hit(&intersections);
         match hit {
@@ -285,10 +319,10 @@ mod ray_tests {
     fn hit_when_some_intersections_have_negative_t() {
         let s = Sphere::new(1);
         let intersections = vec![
-            Intersection { obj: &s, t: -1_f32 },
-            Intersection { obj: &s, t: 4_f32 },
-            Intersection { obj: &s, t: 4_f32 },
-            Intersection { obj: &s, t: -4_f32 },
+            Intersection::new(&s, -1_f32),
+            Intersection::new(&s, 4_f32),
+            Intersection::new(&s, 4_f32),
+            Intersection::new(&s, -4_f32),
         ];
         let hit = hit(&intersections);
         match hit {
@@ -300,8 +334,8 @@ mod ray_tests {
     fn hit_when_all_intersections_have_negative_t() {
         let s = Sphere::new(1);
         let intersections = vec![
-            Intersection { obj: &s, t: -1_f32 },
-            Intersection { obj: &s, t: -4_f32 },
+            Intersection::new(&s, -1_f32),
+            Intersection::new(&s, -4_f32),
         ];
         let hit = hit(&intersections);
         assert!(hit.is_none());
@@ -311,6 +345,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(1_f32, 2_f32, 3_f32),
             direction: Tuple::vector(0_f32, 1_f32, 0_f32),
+            time: 0.0,
         };
         let m = translation(3_f32, 4_f32, 5_f32);
         let ray2 = transform(&ray, &m);
@@ -322,6 +357,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(1_f32, 2_f32, 3_f32),
             direction: Tuple::vector(0_f32, 1_f32, 0_f32),
+            time: 0.0,
         };
         let m = scaling(2_f32, 3_f32, 4_f32);
         let ray2 = transform(&ray, &m);
@@ -333,6 +369,7 @@ mod ray_tests {
         let ray = Ray {
             origin: Tuple::point(0_f32, 0_f32, -5_f32),
             direction: Tuple::vector(0_f32, 0_f32, 1_f32),
+            time: 0.0,
         };
         let mut sphere = Sphere::new(1);
         sphere.set_transformation(scaling(2_f32, 2_f32, 2_f32));
@@ -347,7 +384,7 @@ mod ray_tests {
         let obj_id = 1;
         let ray = Ray::default();
         let sphere = Sphere::new(obj_id);
-        let i = Intersection { t, obj: &sphere };
+        let i = Intersection::new(&sphere, t);
 
         let comps = prepare_computations(&i, &ray, &vec![]);
         assert_eq!(comps.t, t);
@@ -359,10 +396,7 @@ mod ray_tests {
     fn the_hit_when_an_interection_occurs_on_the_outside() {
         let ray = Ray::default();
         let sphere = Sphere::new(1);
-        let i = Intersection {
-            t: 4_f32,
-            obj: &sphere,
-        };
+        let i = Intersection::new(&sphere, 4_f32);
         let comps = prepare_computations(&i, &ray, &vec![]);
         assert_eq!(comps.is_inside, false)
     }
@@ -373,10 +407,7 @@ mod ray_tests {
             Tuple::vector(0_f32, 0_f32, 1_f32),
         );
         let sphere = Sphere::new(1);
-        let i = Intersection {
-            t: 1_f32,
-            obj: &sphere,
-        };
+        let i = Intersection::new(&sphere, 1_f32);
         let comps = prepare_computations(&i, &ray, &vec![]);
         assert_eq!(comps.point, Tuple::point(0_f32, 0_f32, 1_f32));
         assert_eq!(comps.eye_direction, Tuple::vector(0_f32, 0_f32, -1_f32));
@@ -391,10 +422,7 @@ mod ray_tests {
         );
         let mut sphere = Sphere::new(1);
         sphere.set_transformation(Matrix4::identity().translate(0_f32, 0_f32, 1_f32));
-        let i = Intersection {
-            t: 5_f32,
-            obj: &sphere,
-        };
+        let i = Intersection::new(&sphere, 5_f32);
 
         let comps = prepare_computations(&i, &ray, &vec![]);
         assert!(comps.over_point.z < -SHADOW_EPSILON / 2_f32);
@@ -407,10 +435,7 @@ mod ray_tests {
             Tuple::point(0_f32, 1_f32, -1_f32),
             Tuple::vector(0_f32, -(2_f32.sqrt() / 2_f32), 2_f32.sqrt() / 2_f32),
         );
-        let intersection = Intersection {
-            obj: &shape,
-            t: 2_f32.sqrt(),
-        };
+        let intersection = Intersection::new(&shape, 2_f32.sqrt());
         let comps = prepare_computations(&intersection, &ray, &vec![]);
         assert_eq!(
             comps.reflectv,
@@ -441,12 +466,12 @@ mod ray_tests {
             Tuple::vector(0_f32, 0_f32, 1_f32),
         );
         let xs: Vec<Intersection> = vec![
-            Intersection { t: 2.0, obj: &a },
-            Intersection { t: 2.75, obj: &b },
-            Intersection { t: 3.25, obj: &c },
-            Intersection { t: 4.75, obj: &b },
-            Intersection { t: 5.25, obj: &c },
-            Intersection { t: 6_f32, obj: &a },
+            Intersection::new(&a, 2.0),
+            Intersection::new(&b, 2.75),
+            Intersection::new(&c, 3.25),
+            Intersection::new(&b, 4.75),
+            Intersection::new(&c, 5.25),
+            Intersection::new(&a, 6_f32),
         ];
         for i in 0..6 {
             let comps = prepare_computations(&xs[i], &ray, &xs);
@@ -463,14 +488,8 @@ mod ray_tests {
             Tuple::vector(0.0, 1.0, 0.0),
         );
         let xs: Vec<Intersection> = vec![
-            Intersection {
-                t: -(2_f32.sqrt() / 2.0),
-                obj: &shape,
-            },
-            Intersection {
-                t: (2_f32.sqrt() / 2.0),
-                obj: &shape,
-            },
+            Intersection::new(&shape, -(2_f32.sqrt() / 2.0)),
+            Intersection::new(&shape, 2_f32.sqrt() / 2.0),
         ];
         let comps = prepare_computations(&xs[1], &ray, &xs);
         let reflectance = schlick(&comps);
@@ -482,14 +501,8 @@ mod ray_tests {
         let shape = Sphere::new_glass(1);
         let ray = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
         let xs: Vec<Intersection> = vec![
-            Intersection {
-                t: -1.0,
-                obj: &shape,
-            },
-            Intersection {
-                t: 1.0,
-                obj: &shape,
-            },
+            Intersection::new(&shape, -1.0),
+            Intersection::new(&shape, 1.0),
         ];
         let comps = prepare_computations(&xs[1], &ray, &xs);
         let reflectance = schlick(&comps);
@@ -500,10 +513,7 @@ mod ray_tests {
     fn the_schlick_approximation_with_a_small_angle_and_n2_gt_n1() {
         let shape = Sphere::new_glass(1);
         let ray = Ray::new(Tuple::point(0.0, 0.99, -2.0), Tuple::vector(0.0, 0.0, 1.0));
-        let xs: Vec<Intersection> = vec![Intersection {
-            t: 1.8589,
-            obj: &shape,
-        }];
+        let xs: Vec<Intersection> = vec![Intersection::new(&shape, 1.8589)];
         let comps = prepare_computations(&xs[0], &ray, &xs);
         let reflectance = schlick(&comps);
         assert!((reflectance - 0.48873_f32).abs() < EPSILON);
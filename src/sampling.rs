@@ -0,0 +1,341 @@
+use crate::math::PI;
+use crate::random::Rng;
+
+// Where the samples of a pixel go. Each pattern gives samples_per_side² samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SamplePattern {
+    // The centers of a regular grid.
+    Grid,
+    // One random sample in each cell of the grid.
+    Jittered,
+    // Uniformly random over the whole pixel.
+    Random,
+}
+
+// Reconstruction filter, weighting samples by their distance from the pixel center in
+// pixels. Filters wider than a pixel also sample some of the neighbouring pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Gaussian { alpha: f32 },
+    // Mitchell-Netravali, with B = C = 1/3 being the usual choice.
+    Mitchell { b: f32, c: f32 },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian { .. } => 1.5,
+            Filter::Mitchell { .. } => 2.0,
+        }
+    }
+
+    pub fn weight(&self, dx: f32, dy: f32) -> f32 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    // The areas under the positive and the negative parts of the filter, both as positive
+    // numbers. Only Mitchell has a negative part.
+    pub fn lobe_areas(&self) -> (f32, f32) {
+        let step = 2.0 * self.radius() / TABLE_SIZE as f32;
+        let (mut positive, mut negative) = (0.0, 0.0);
+        for i in 0..TABLE_SIZE {
+            let weight = self.weight_1d(-self.radius() + (i as f32 + 0.5) * step) * step;
+            if weight < 0.0 {
+                negative -= weight;
+            } else {
+                positive += weight;
+            }
+        }
+        // The filter is separable, so two lobes of the same sign give a positive one.
+        (positive * positive + negative * negative, 2.0 * positive * negative)
+    }
+
+    // The area under |weight_1d| from the left of the footprint, at TABLE_SIZE + 1 evenly
+    // spaced offsets, scaled to end at 1.
+    fn cumulative_magnitude(&self) -> Vec<f32> {
+        let step = 2.0 * self.radius() / TABLE_SIZE as f32;
+        let mut cdf = Vec::with_capacity(TABLE_SIZE + 1);
+        cdf.push(0.0);
+        for i in 0..TABLE_SIZE {
+            let weight = self.weight_1d(-self.radius() + (i as f32 + 0.5) * step).abs();
+            cdf.push(cdf[i] + weight);
+        }
+        let total = cdf[TABLE_SIZE];
+        cdf.iter().map(|value| value / total).collect()
+    }
+
+    // Maps u in [0, 1] to an offset in pixels, so that evenly spread u give offsets spread in
+    // proportion to |weight_1d|.
+    fn sample_1d(&self, cdf: &[f32], u: f32) -> f32 {
+        let index = usize::min(cdf.partition_point(|value| *value <= u), TABLE_SIZE).max(1) - 1;
+        let width = cdf[index + 1] - cdf[index];
+        let fraction = if width > 0.0 { (u - cdf[index]) / width } else { 0.5 };
+        let step = 2.0 * self.radius() / TABLE_SIZE as f32;
+        -self.radius() + (index as f32 + fraction.clamp(0.0, 1.0)) * step
+    }
+
+    fn weight_1d(&self, d: f32) -> f32 {
+        let d = d.abs();
+        let radius = self.radius();
+        if d > radius {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - d,
+            // Without a positive alpha there is no bell shape, which would leave the filter
+            // zero everywhere, so it is flat instead.
+            Filter::Gaussian { alpha } if *alpha <= 0.0 => 1.0,
+            // Shifted down so that it reaches zero at the radius instead of being cut off.
+            Filter::Gaussian { alpha } => {
+                f32::max(0.0, (-alpha * d * d).exp() - (-alpha * radius * radius).exp())
+            }
+            Filter::Mitchell { b, c } => {
+                let d2 = d * d;
+                let d3 = d2 * d;
+                if d < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * d3
+                        + (-18.0 + 12.0 * b + 6.0 * c) * d2
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else {
+                    ((-b - 6.0 * c) * d3
+                        + (6.0 * b + 30.0 * c) * d2
+                        + (-12.0 * b - 48.0 * c) * d
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                }
+            }
+        }
+    }
+}
+
+// The resolution the filters are tabulated at, for sampling.
+const TABLE_SIZE: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampler {
+    pub pattern: SamplePattern,
+    pub samples_per_side: usize,
+    pub filter: Filter,
+}
+
+impl Sampler {
+    pub fn new(pattern: SamplePattern, samples_per_side: usize, filter: Filter) -> Sampler {
+        Sampler {
+            pattern,
+            samples_per_side,
+            filter,
+        }
+    }
+
+    // One ray through the center of every pixel, i.e. no anti-aliasing.
+    pub fn single() -> Sampler {
+        Sampler::new(SamplePattern::Grid, 1, Filter::Box)
+    }
+
+    // Sample offsets from the pixel center, in pixels, spread over the filter footprint in
+    // proportion to the magnitude of the filter. That makes every sample count the same,
+    // apart from the sign of the filter where it is negative.
+    pub fn offsets(&self, rng: &mut Rng) -> Vec<(f32, f32)> {
+        let n = usize::max(self.samples_per_side, 1);
+        let cdf = self.filter.cumulative_magnitude();
+        let mut offsets = Vec::with_capacity(n * n);
+        for j in 0..n {
+            for i in 0..n {
+                let (u, v) = match self.pattern {
+                    SamplePattern::Grid => ((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32),
+                    SamplePattern::Jittered => (
+                        (i as f32 + rng.next_f32()) / n as f32,
+                        (j as f32 + rng.next_f32()) / n as f32,
+                    ),
+                    SamplePattern::Random => (rng.next_f32(), rng.next_f32()),
+                };
+                offsets.push((self.filter.sample_1d(&cdf, u), self.filter.sample_1d(&cdf, v)));
+            }
+        }
+        offsets
+    }
+}
+
+// Maps a point of the unit square to the unit disk, keeping evenly spread points evenly
+// spread (Shirley's concentric mapping). The center of the square maps to the center.
+pub fn sample_disk(u: f32, v: f32) -> (f32, f32) {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+// Uniform point in a regular polygon with its corners on the unit circle, one pointing up.
+// u picks one of the triangles between the center and an edge, and is then reused.
+pub fn sample_polygon(sides: u32, u: f32, v: f32) -> (f32, f32) {
+    let scaled = u * sides as f32;
+    let side = u32::min(scaled as u32, sides - 1);
+    let u = scaled - side as f32;
+
+    let corner = |index: u32| {
+        let angle = PI / 2.0 + 2.0 * PI * index as f32 / sides as f32;
+        (angle.cos(), angle.sin())
+    };
+    let (ax, ay) = corner(side);
+    let (bx, by) = corner(side + 1);
+    // Uniform in the triangle (center, a, b).
+    let s = u.sqrt();
+    let wa = s * (1.0 - v);
+    let wb = s * v;
+    (wa * ax + wb * bx, wa * ay + wb * by)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::PI;
+    use crate::random::Rng;
+    use crate::sampling::{sample_disk, sample_polygon, Filter, SamplePattern, Sampler};
+    use core::f32::consts::FRAC_1_SQRT_2;
+
+    const MITCHELL: Filter = Filter::Mitchell { b: 1.0 / 3.0, c: 1.0 / 3.0 };
+
+    #[test]
+    fn a_regular_grid_samples_the_centers_of_its_cells() {
+        let sampler = Sampler::new(SamplePattern::Grid, 2, Filter::Box);
+        let offsets = sampler.offsets(&mut Rng::new(1));
+        assert_eq!(offsets, vec![(-0.25, -0.25), (0.25, -0.25), (-0.25, 0.25), (0.25, 0.25)]);
+        assert_eq!(Sampler::single().offsets(&mut Rng::new(1)), vec![(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn jittered_samples_stay_in_their_cells() {
+        // Box weights are the same everywhere, so the cells are a quarter of the pixel wide.
+        let sampler = Sampler::new(SamplePattern::Jittered, 4, Filter::Box);
+        let mut rng = Rng::new(7);
+        for _ in 0..20 {
+            let offsets = sampler.offsets(&mut rng);
+            assert_eq!(offsets.len(), 16);
+            for (index, (dx, dy)) in offsets.iter().enumerate() {
+                let i = (index % 4) as f32;
+                let j = (index / 4) as f32;
+                assert!(*dx >= -0.5 + i * 0.25 - 0.0001 && *dx <= -0.5 + (i + 1.0) * 0.25 + 0.0001);
+                assert!(*dy >= -0.5 + j * 0.25 - 0.0001 && *dy <= -0.5 + (j + 1.0) * 0.25 + 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn samples_follow_the_magnitude_of_the_filter() {
+        let sampler = Sampler::new(SamplePattern::Random, 64, MITCHELL);
+        let offsets = sampler.offsets(&mut Rng::new(3));
+        assert_eq!(offsets.len(), 4096);
+        assert!(offsets.iter().all(|(dx, dy)| dx.abs() <= 2.0 && dy.abs() <= 2.0));
+        // The tails are light, but still sampled, as they are where the filter is negative.
+        let negative = offsets.iter().filter(|(dx, dy)| MITCHELL.weight(*dx, *dy) < 0.0).count() as f32;
+        let (positive_area, negative_area) = MITCHELL.lobe_areas();
+        let expected = 4096.0 * negative_area / (positive_area + negative_area);
+        assert!((negative - expected).abs() < 0.2 * expected, "{} instead of {}", negative, expected);
+
+        // The tent's weight at 0.5 is half of that at 0, and so are the samples around it.
+        let sampler = Sampler::new(SamplePattern::Random, 64, Filter::Tent);
+        let offsets = sampler.offsets(&mut Rng::new(5));
+        let near = |d: f32| offsets.iter().filter(|(dx, _)| (dx - d).abs() < 0.05).count() as f32;
+        let ratio = near(0.5) / near(0.0);
+        assert!(ratio > 0.4 && ratio < 0.6, "{}", ratio);
+    }
+
+    #[test]
+    fn lobe_areas() {
+        let test_cases = vec![
+            (Filter::Box, 1.0, 0.0),
+            (Filter::Tent, 1.0, 0.0),
+            (MITCHELL, 1.07242, 0.07242),
+        ];
+        for (filter, expected_positive, expected_negative) in test_cases {
+            let (positive, negative) = filter.lobe_areas();
+            assert!((positive - expected_positive).abs() < 0.0001, "{:?}: {}", filter, positive);
+            assert!((negative - expected_negative).abs() < 0.0001, "{:?}: {}", filter, negative);
+        }
+    }
+
+    #[test]
+    fn filter_weights() {
+        let gaussian = Filter::Gaussian { alpha: 2.0 };
+        let test_cases = vec![
+            (Filter::Box, 0.0, 1.0),
+            (Filter::Box, 0.5, 1.0),
+            (Filter::Box, 0.6, 0.0),
+            (Filter::Tent, 0.0, 1.0),
+            (Filter::Tent, 0.25, 0.75),
+            (Filter::Tent, 1.0, 0.0),
+            (gaussian, 0.0, 0.98889),
+            (gaussian, 1.0, 0.12423),
+            (gaussian, 1.5, 0.0),
+            (Filter::Gaussian { alpha: 0.0 }, 1.0, 1.0),
+            (Filter::Gaussian { alpha: -1.0 }, 1.6, 0.0),
+            (MITCHELL, 0.0, 0.88889),
+            (MITCHELL, 1.0, 0.05556),
+            (MITCHELL, 1.5, -0.03472),
+            (MITCHELL, 2.0, 0.0),
+            (MITCHELL, 2.5, 0.0),
+        ];
+        for (filter, d, expected) in test_cases {
+            let actual = filter.weight_1d(d);
+            assert!((actual - expected).abs() < 0.0001, "{:?} at {}: {}", filter, d, actual);
+        }
+    }
+
+    #[test]
+    fn a_gaussian_without_a_positive_alpha_is_flat() {
+        let sampler = Sampler::new(SamplePattern::Random, 4, Filter::Gaussian { alpha: -1.0 });
+        let offsets = sampler.offsets(&mut Rng::new(9));
+        assert!(offsets.iter().all(|(dx, dy)| dx.abs() <= 1.5 && dy.abs() <= 1.5));
+        assert_eq!(sampler.filter.lobe_areas().1, 0.0);
+        assert!((sampler.filter.lobe_areas().0 - 9.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn disk_samples_stay_in_the_unit_disk() {
+        assert_eq!(sample_disk(0.5, 0.5), (0.0, 0.0));
+        let (x, y) = sample_disk(1.0, 0.5);
+        assert!((x - 1.0).abs() < 0.0001 && y.abs() < 0.0001);
+        let mut rng = Rng::new(11);
+        let mut outer = 0;
+        for _ in 0..1000 {
+            let (x, y) = sample_disk(rng.next_f32(), rng.next_f32());
+            let radius = (x * x + y * y).sqrt();
+            assert!(radius <= 1.0001);
+            if radius > FRAC_1_SQRT_2 {
+                outer += 1;
+            }
+        }
+        // Half the area of the disk is outside radius 1/sqrt(2).
+        assert!(outer > 430 && outer < 570, "{}", outer);
+    }
+
+    #[test]
+    fn polygon_samples_stay_in_the_polygon() {
+        let sides = 6;
+        let mut rng = Rng::new(5);
+        let mut used_sides = [false; 6];
+        for _ in 0..1000 {
+            let (x, y) = sample_polygon(sides, rng.next_f32(), rng.next_f32());
+            // Inside every edge, whose midpoints are cos(pi / 6) from the center.
+            for side in 0..sides {
+                let angle = PI / 2.0 + 2.0 * PI * (side as f32 + 0.5) / sides as f32;
+                assert!(x * angle.cos() + y * angle.sin() <= (PI / 6.0).cos() + 0.0001);
+            }
+            let angle = (y.atan2(x) - PI / 2.0).rem_euclid(2.0 * PI);
+            used_sides[(angle / (PI / 3.0)) as usize % 6] = true;
+        }
+        assert!(used_sides.iter().all(|used| *used));
+    }
+}
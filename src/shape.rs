@@ -1,3 +1,4 @@
+use crate::bounds::BoundingBox;
 use crate::material::Material;
 use crate::ray::Intersection;
 use crate::Matrix4;
@@ -7,11 +8,39 @@ use crate::Tuple;
 pub trait Shape: Sync + Send {
     fn get_id(&self) -> u32;
     fn set_transformation(&mut self, t: Matrix4);
+    fn get_transformation(&self) -> &Matrix4;
+    // Called by the group owning the shape. The inverse transformation then converts
+    // all the way from world space to object space, through every parent.
+    fn set_parent_transformation(&mut self, parent: &Matrix4);
     fn get_inverse_transformation(&self) -> &Matrix4;
     fn normal_at(&self, world_point: &Tuple) -> Tuple;
+    // Shapes that need more than the point to find the normal, e.g. smooth triangles
+    // interpolating with u/v, can override this.
+    fn normal_at_hit(&self, world_point: &Tuple, _hit: &Intersection) -> Tuple {
+        self.normal_at(world_point)
+    }
     fn get_material(&self) -> &Material;
     fn get_mut_material(&mut self) -> &mut Material;
     fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>>;
+    // True if the ray hits the shape somewhere in [0, max_distance). Shadow rays only need
+    // to know if something is in the way, so groups override this to stop at the first hit.
+    fn occludes(&self, ray: &Ray, max_distance: f32) -> bool {
+        self.intersections_by(ray)
+            .iter()
+            .any(|i| i.t >= 0.0 && i.t < max_distance)
+    }
+    // The bounds of the untransformed shape, in object space.
+    fn bounds(&self) -> BoundingBox;
+    // The bounds in the space of the parent group, or world space for top level shapes.
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(self.get_transformation())
+    }
+    // Shapes containing other shapes build acceleration structures for them.
+    fn build_bvh(&mut self) {}
+    // True if other is this shape, or one of its descendants for groups and CSG.
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.get_id() == other.get_id()
+    }
 }
 
 impl PartialEq for dyn Shape {
@@ -0,0 +1,174 @@
+use crate::Shape;
+use crate::bounds::BoundingBox;
+use crate::material::Material;
+use crate::matrix::inverse4;
+use crate::ray::Intersection;
+use crate::triangle::intersect_triangle;
+use crate::Matrix4;
+use crate::Ray;
+use crate::Tuple;
+use crate::ray::transform;
+
+pub struct SmoothTriangle {
+    pub id: u32,
+    pub material: Material,
+    pub transformation: Matrix4,
+    pub inverse_transformation: Matrix4,
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub n1: Tuple,
+    pub n2: Tuple,
+    pub n3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+}
+
+impl SmoothTriangle {
+    pub fn new(id: u32, p1: Tuple, p2: Tuple, p3: Tuple, n1: Tuple, n2: Tuple, n3: Tuple) -> SmoothTriangle {
+        SmoothTriangle {
+            id,
+            material: Material::default(),
+            transformation: Matrix4::identity(),
+            inverse_transformation: Matrix4::identity(),
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1: p2 - p1,
+            e2: p3 - p1,
+        }
+    }
+
+    fn interpolated_normal(&self, u: f32, v: f32) -> Tuple {
+        let normal = self.n2 * u + self.n3 * v + self.n1 * (1.0 - u - v);
+        let mut world_normal = &self.inverse_transformation.transpose() * &normal;
+        world_normal.w = 0_f32; // Hack. Should actually find submatrix 3x3, and multiply with the inverse of that, to avoid messing with w. But this is fine and faster.
+        world_normal.normalize()
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.inverse_transformation = inverse4(&self.transformation)
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.inverse_transformation = inverse4(&(parent * &self.transformation))
+    }
+
+    // Without a hit we have to find u/v from the point itself, by solving
+    // point = p1 + u * e1 + v * e2.
+    fn normal_at(&self, world_point: &Tuple) -> Tuple {
+        let point = &self.inverse_transformation * world_point;
+        let p1_to_point = point - self.p1;
+        let d11 = self.e1.dot(&self.e1);
+        let d12 = self.e1.dot(&self.e2);
+        let d22 = self.e2.dot(&self.e2);
+        let d1p = self.e1.dot(&p1_to_point);
+        let d2p = self.e2.dot(&p1_to_point);
+        let denominator = d11 * d22 - d12 * d12;
+        let u = (d22 * d1p - d12 * d2p) / denominator;
+        let v = (d11 * d2p - d12 * d1p) / denominator;
+        self.interpolated_normal(u, v)
+    }
+
+    fn normal_at_hit(&self, _: &Tuple, hit: &Intersection) -> Tuple {
+        self.interpolated_normal(hit.u, hit.v)
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_mut_material(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        let ray = transform(ray, &self.inverse_transformation);
+        match intersect_triangle(&self.p1, &self.e1, &self.e2, &ray) {
+            Some((t, u, v)) => vec![Intersection::new_with_uv(self, t, u, v)],
+            None => vec![],
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        bounds.add_point(&self.p1);
+        bounds.add_point(&self.p2);
+        bounds.add_point(&self.p3);
+        bounds
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix4 {
+        &self.inverse_transformation
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::ray::{prepare_computations, Intersection};
+    use crate::smooth_triangle::SmoothTriangle;
+    use crate::Shape;
+    use crate::tuple::Tuple;
+    use crate::Ray;
+
+    fn default_smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            1,
+            Tuple::point(0.0, 1.0, 0.0),
+            Tuple::point(-1.0, 0.0, 0.0),
+            Tuple::point(1.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+            Tuple::vector(-1.0, 0.0, 0.0),
+            Tuple::vector(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn an_intersection_with_a_smooth_triangle_stores_u_and_v() {
+        let triangle = default_smooth_triangle();
+        let ray = Ray::new(Tuple::point(-0.2, 0.3, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = triangle.intersections_by(&ray);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].u - 0.45).abs() < 0.0001);
+        assert!((xs[0].v - 0.25).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_smooth_triangle_uses_u_and_v_to_interpolate_the_normal() {
+        let triangle = default_smooth_triangle();
+        let i = Intersection::new_with_uv(&triangle, 1.0, 0.45, 0.25);
+        let normal = triangle.normal_at_hit(&Tuple::point(0.0, 0.0, 0.0), &i);
+        assert_eq!(normal, Tuple::vector(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn the_normal_without_a_hit_is_interpolated_from_the_point() {
+        let triangle = default_smooth_triangle();
+        let normal = triangle.normal_at(&Tuple::point(-0.2, 0.3, 0.0));
+        assert_eq!(normal, Tuple::vector(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn preparing_the_normal_on_a_smooth_triangle() {
+        let triangle = default_smooth_triangle();
+        let ray = Ray::new(Tuple::point(-0.2, 0.3, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = vec![Intersection::new_with_uv(&triangle, 1.0, 0.45, 0.25)];
+        let comps = prepare_computations(&xs[0], &ray, &xs);
+        assert_eq!(comps.surface_normalv, Tuple::vector(-0.5547, 0.83205, 0.0));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::bounds::BoundingBox;
 use crate::material::Material;
 use crate::matrix::{inverse4, Matrix4};
 use crate::ray::transform;
@@ -30,6 +31,10 @@ impl Shape for Sphere {
         self.inverse_transformation = inverse4(&self.transformation)
     }
 
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.inverse_transformation = inverse4(&(parent * &self.transformation))
+    }
+
     fn normal_at(&self, world_point: &Tuple) -> Tuple {
         let object_point = &self.inverse_transformation * world_point;
         let object_normal = (object_point - Tuple::point(0_f32, 0_f32, 0_f32)).normalize();
@@ -65,19 +70,21 @@ impl Shape for Sphere {
         if discriminant < 0_f32 {
             return result;
         }
-        let i1 = Intersection {
-            obj: self,
-            t: (-b - discriminant.sqrt()) / (2_f32 * a),
-        };
-        let i2 = Intersection {
-            obj: self,
-            t: (-b + discriminant.sqrt()) / (2_f32 * a),
-        };
+        let i1 = Intersection::new(self, (-b - discriminant.sqrt()) / (2_f32 * a));
+        let i2 = Intersection::new(self, (-b + discriminant.sqrt()) / (2_f32 * a));
         result.push(i1);
         result.push(i2);
         result
     }
 
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0))
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
     fn get_inverse_transformation(&self) -> &Matrix4 {
         &self.inverse_transformation
     }
@@ -168,5 +175,16 @@ mod tests {
         ));
         assert_eq!(normal, Tuple::vector(0_f32, 0.97014_f32, -0.24254_f32));
     }
-    
+
+    #[test]
+    fn a_sphere_has_a_bounding_box() {
+        let mut sphere = Sphere::new(1);
+        sphere.set_transformation(Matrix4::identity().translate(1_f32, -3_f32, 5_f32).scale(0.5_f32, 2_f32, 4_f32));
+        let bounds = sphere.bounds();
+        assert_eq!(bounds.min, Tuple::point(-1_f32, -1_f32, -1_f32));
+        assert_eq!(bounds.max, Tuple::point(1_f32, 1_f32, 1_f32));
+        let bounds = sphere.parent_space_bounds();
+        assert_eq!(bounds.min, Tuple::point(0.5_f32, -5_f32, 1_f32));
+        assert_eq!(bounds.max, Tuple::point(1.5_f32, -1_f32, 9_f32));
+    }
 }
@@ -0,0 +1,224 @@
+use crate::camera::{render_pixel, Camera};
+use crate::canvas::Canvas;
+use crate::sampling::Sampler;
+use crate::transformation::view_transform;
+use crate::world::World;
+use crate::Tuple;
+
+// How the eyes of a stereo rig are aimed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Convergence {
+    // Both eyes look straight ahead, so everything appears in front of the screen.
+    Parallel,
+    // Both eyes are turned towards the point looked at. Simple, but the image planes are
+    // no longer parallel, which gives some vertical disparity towards the corners.
+    ToeIn,
+    // Both eyes look straight ahead, with their canvases shifted so that they line up at
+    // the point looked at. The usual choice for comfortable viewing.
+    OffAxis,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Packing {
+    // The left eye in the left half, and the right eye in the right half.
+    SideBySide,
+    // The left eye in the top half, and the right eye in the bottom half.
+    TopBottom,
+}
+
+// Two cameras, one for each eye. Set the distance between the eyes and the convergence
+// first, then place the rig with set_view. Other settings, like the aperture, can be made
+// on the cameras directly.
+pub struct StereoRig {
+    pub left: Camera,
+    pub right: Camera,
+    pub interocular_distance: f32,
+    pub convergence: Convergence,
+}
+
+impl StereoRig {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: f32) -> StereoRig {
+        StereoRig {
+            left: Camera::new(hsize, vsize, field_of_view),
+            right: Camera::new(hsize, vsize, field_of_view),
+            // The average for adults, in meters.
+            interocular_distance: 0.064,
+            convergence: Convergence::OffAxis,
+        }
+    }
+
+    // Like view_transform for a single camera. The eyes are placed on either side of from,
+    // and converge at to, if they converge at all.
+    pub fn set_view(&mut self, from: &Tuple, to: &Tuple, up: &Tuple) {
+        let forward = *to - *from;
+        let distance = forward.mag();
+        let forward = forward.normalize();
+        let left = forward.cross(&up.normalize()).normalize();
+        let half_distance = self.interocular_distance / 2.0;
+
+        let eyes = [(&mut self.left, half_distance), (&mut self.right, -half_distance)];
+        for (camera, offset) in eyes {
+            let eye = *from + left * offset;
+            let target = match self.convergence {
+                Convergence::ToeIn => *to,
+                Convergence::Parallel | Convergence::OffAxis => *to + left * offset,
+            };
+            camera.set_transform(&view_transform(&eye, &target, up));
+            // Where the point looked at is on the canvas, 1 unit in front of the eye.
+            camera.shift_x = match self.convergence {
+                Convergence::OffAxis => -offset / distance,
+                Convergence::Parallel | Convergence::ToeIn => 0.0,
+            };
+        }
+    }
+
+    pub fn render(&self, world: &World, sampler: &Sampler) -> (Canvas, Canvas) {
+        (render_camera(&self.left, world, sampler), render_camera(&self.right, world, sampler))
+    }
+
+    pub fn render_packed(&self, world: &World, sampler: &Sampler, packing: Packing) -> Canvas {
+        let (left, right) = self.render(world, sampler);
+        pack(&left, &right, packing)
+    }
+}
+
+fn render_camera(camera: &Camera, world: &World, sampler: &Sampler) -> Canvas {
+    let mut canvas = Canvas::new(camera.hsize, camera.vsize);
+    for y in 0..camera.vsize {
+        for x in 0..camera.hsize {
+            canvas.set_pixel(x, y, render_pixel(x, y, camera, world, sampler));
+        }
+    }
+    canvas
+}
+
+// Puts the images for both eyes into one, as expected by most VR and 3D players.
+pub fn pack(left: &Canvas, right: &Canvas, packing: Packing) -> Canvas {
+    let (x_offset, y_offset) = match packing {
+        Packing::SideBySide => (left.width, 0),
+        Packing::TopBottom => (0, left.height),
+    };
+    let mut canvas = Canvas::new(
+        usize::max(left.width, x_offset + right.width),
+        usize::max(left.height, y_offset + right.height),
+    );
+    for y in 0..left.height {
+        for x in 0..left.width {
+            canvas.set_pixel(x, y, left.pixel_at(x, y));
+        }
+    }
+    for y in 0..right.height {
+        for x in 0..right.width {
+            canvas.set_pixel(x + x_offset, y + y_offset, right.pixel_at(x, y));
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canvas::Canvas;
+    use crate::color::{BLACK, WHITE};
+    use crate::sampling::Sampler;
+    use crate::stereo::{pack, Convergence, Packing, StereoRig};
+    use crate::world::World;
+    use crate::Color;
+    use crate::Matrix4;
+    use crate::Ray;
+    use crate::Tuple;
+    use core::f32::consts::PI;
+
+    fn rig(convergence: Convergence) -> StereoRig {
+        let mut rig = StereoRig::new(101, 51, PI / 2.0);
+        rig.interocular_distance = 2.0;
+        rig.convergence = convergence;
+        rig.set_view(
+            &Tuple::point(0.0, 0.0, -10.0),
+            &Tuple::point(0.0, 0.0, 0.0),
+            &Tuple::vector(0.0, 1.0, 0.0),
+        );
+        rig
+    }
+
+    #[test]
+    fn the_eyes_are_placed_on_either_side() {
+        let rig = rig(Convergence::Parallel);
+        let left = rig.left.ray_for_pixel(50, 25);
+        let right = rig.right.ray_for_pixel(50, 25);
+        assert_eq!(left.origin, Tuple::point(-1.0, 0.0, -10.0));
+        assert_eq!(right.origin, Tuple::point(1.0, 0.0, -10.0));
+        assert_eq!(left.direction, Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(right.direction, Tuple::vector(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn converging_eyes_see_the_point_looked_at_in_the_middle() {
+        for convergence in [Convergence::ToeIn, Convergence::OffAxis] {
+            let rig = rig(convergence);
+            for camera in [&rig.left, &rig.right] {
+                let ray = camera.ray_for_pixel(50, 25);
+                let t = ray.origin.mag();
+                assert_eq!(ray.position(t), Tuple::point(0.0, 0.0, 0.0), "{:?}", convergence);
+            }
+        }
+
+        // With off axis cameras, the same pixel of both eyes sees the same point anywhere on
+        // the plane in focus. Toed in cameras only line up in the middle.
+        let in_focus = |ray: Ray| ray.position(-ray.origin.z / ray.direction.z);
+        let rig = rig(Convergence::OffAxis);
+        assert_eq!(in_focus(rig.left.ray_for_pixel(0, 0)), in_focus(rig.right.ray_for_pixel(0, 0)));
+        let rig = self::rig(Convergence::ToeIn);
+        assert!(in_focus(rig.left.ray_for_pixel(0, 0)) != in_focus(rig.right.ray_for_pixel(0, 0)));
+    }
+
+    #[test]
+    fn packing_both_eyes_into_one_image() {
+        let mut left = Canvas::new(2, 1);
+        left.set_pixel(0, 0, WHITE);
+        left.set_pixel(1, 0, Color::new(0.5, 0.5, 0.5));
+        let mut right = Canvas::new(2, 1);
+        right.set_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+
+        let side_by_side = pack(&left, &right, Packing::SideBySide);
+        assert_eq!((side_by_side.width, side_by_side.height), (4, 1));
+        assert_eq!(side_by_side.pixel_at(1, 0), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(side_by_side.pixel_at(2, 0), Color::new(1.0, 0.0, 0.0));
+
+        let top_bottom = pack(&left, &right, Packing::TopBottom);
+        assert_eq!((top_bottom.width, top_bottom.height), (2, 2));
+        assert_eq!(top_bottom.pixel_at(0, 0), WHITE);
+        assert_eq!(top_bottom.pixel_at(0, 1), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(top_bottom.pixel_at(1, 1), BLACK);
+    }
+
+    #[test]
+    fn rendering_a_stereo_pair() {
+        // A small white sphere in front of the point looked at, which the eyes see on
+        // different sides of the middle of the image.
+        let world = World::glowing_sphere(Matrix4::identity().translate(0.0, 0.0, -5.0).scale(0.5, 0.5, 0.5));
+        let mut rig = StereoRig::new(21, 11, PI / 3.0);
+        rig.interocular_distance = 2.0;
+        rig.set_view(
+            &Tuple::point(0.0, 0.0, -10.0),
+            &Tuple::point(0.0, 0.0, 0.0),
+            &Tuple::vector(0.0, 1.0, 0.0),
+        );
+
+        let (left, right) = rig.render(&world, &Sampler::single());
+        // The middle of the bright pixels in the middle row.
+        let middle = |canvas: &Canvas| {
+            let total: f32 = (0..canvas.width).map(|x| canvas.pixel_at(x, 5).red).sum();
+            (0..canvas.width).map(|x| x as f32 * canvas.pixel_at(x, 5).red).sum::<f32>() / total
+        };
+        // Closer than the point looked at, so crossed: the left eye sees it to the right.
+        assert!(middle(&left) > 10.5, "{}", middle(&left));
+        assert!(middle(&right) < 9.5, "{}", middle(&right));
+
+        let packed = rig.render_packed(&world, &Sampler::single(), Packing::SideBySide);
+        assert_eq!(packed.width, 42);
+        for x in 0..21 {
+            assert_eq!(packed.pixel_at(x, 5), left.pixel_at(x, 5));
+            assert_eq!(packed.pixel_at(21 + x, 5), right.pixel_at(x, 5));
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::math::EPSILON;
+use crate::Shape;
+use crate::bounds::BoundingBox;
+use crate::material::Material;
+use crate::matrix::inverse4;
+use crate::ray::Intersection;
+use crate::Matrix4;
+use crate::Ray;
+use crate::Tuple;
+use crate::ray::transform;
+
+pub struct Triangle {
+    pub id: u32,
+    pub material: Material,
+    pub transformation: Matrix4,
+    pub inverse_transformation: Matrix4,
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    pub normal: Tuple,
+}
+
+impl Triangle {
+    pub fn new(id: u32, p1: Tuple, p2: Tuple, p3: Tuple) -> Triangle {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Triangle {
+            id,
+            material: Material::default(),
+            transformation: Matrix4::identity(),
+            inverse_transformation: Matrix4::identity(),
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(&e1).normalize(),
+        }
+    }
+}
+
+impl Shape for Triangle {
+    fn set_transformation(&mut self, t: Matrix4) {
+        self.transformation = t;
+        self.inverse_transformation = inverse4(&self.transformation)
+    }
+
+    fn set_parent_transformation(&mut self, parent: &Matrix4) {
+        self.inverse_transformation = inverse4(&(parent * &self.transformation))
+    }
+
+    fn normal_at(&self, _: &Tuple) -> Tuple {
+        let mut world_normal = &self.inverse_transformation.transpose() * &self.normal;
+        world_normal.w = 0_f32; // Hack. Should actually find submatrix 3x3, and multiply with the inverse of that, to avoid messing with w. But this is fine and faster.
+        world_normal.normalize()
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_mut_material(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersections_by<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        let ray = transform(ray, &self.inverse_transformation);
+        match intersect_triangle(&self.p1, &self.e1, &self.e2, &ray) {
+            Some((t, u, v)) => vec![Intersection::new_with_uv(self, t, u, v)],
+            None => vec![],
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
+        bounds.add_point(&self.p1);
+        bounds.add_point(&self.p2);
+        bounds.add_point(&self.p3);
+        bounds
+    }
+
+    fn get_transformation(&self) -> &Matrix4 {
+        &self.transformation
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix4 {
+        &self.inverse_transformation
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+}
+
+// Möller–Trumbore. Returns t and the barycentric u/v of the hit, measured along e1 and e2.
+pub fn intersect_triangle(p1: &Tuple, e1: &Tuple, e2: &Tuple, ray: &Ray) -> Option<(f32, f32, f32)> {
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let determinant = e1.dot(&dir_cross_e2);
+    if determinant.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / determinant;
+    let p1_to_origin = ray.origin - *p1;
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+    if v < 0.0 || (u + v) > 1.0 {
+        return None;
+    }
+
+    let t = f * e2.dot(&origin_cross_e1);
+    Some((t, u, v))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::triangle::Triangle;
+    use crate::Shape;
+    use crate::tuple::Tuple;
+    use crate::Ray;
+
+    fn default_triangle() -> Triangle {
+        Triangle::new(
+            1,
+            Tuple::point(0.0, 1.0, 0.0),
+            Tuple::point(-1.0, 0.0, 0.0),
+            Tuple::point(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_a_triangle() {
+        let triangle = default_triangle();
+        assert_eq!(triangle.e1, Tuple::vector(-1.0, -1.0, 0.0));
+        assert_eq!(triangle.e2, Tuple::vector(1.0, -1.0, 0.0));
+        assert_eq!(triangle.normal, Tuple::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_triangle() {
+        let triangle = default_triangle();
+        let n1 = triangle.normal_at(&Tuple::point(0.0, 0.5, 0.0));
+        let n2 = triangle.normal_at(&Tuple::point(-0.5, 0.75, 0.0));
+        let n3 = triangle.normal_at(&Tuple::point(0.5, 0.25, 0.0));
+        assert_eq!(n1, triangle.normal);
+        assert_eq!(n2, triangle.normal);
+        assert_eq!(n3, triangle.normal);
+    }
+
+    #[test]
+    fn a_ray_misses_a_triangle() {
+        let test_cases = vec![
+            (Tuple::point(0.0, -1.0, -2.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(1.0, 1.0, -2.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(-1.0, 1.0, -2.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(0.0, -1.0, -2.0), Tuple::vector(0.0, 0.0, 1.0)),
+        ];
+        let triangle = default_triangle();
+
+        for test_case in test_cases {
+            let ray = Ray::new(test_case.0, test_case.1);
+            let xs = triangle.intersections_by(&ray);
+            assert_eq!(xs.len(), 0, "Origin: {}", test_case.0);
+        }
+    }
+
+    #[test]
+    fn a_ray_strikes_a_triangle() {
+        let triangle = default_triangle();
+        let ray = Ray::new(Tuple::point(0.0, 0.5, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = triangle.intersections_by(&ray);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+    }
+
+    #[test]
+    fn a_triangle_has_a_bounding_box() {
+        let triangle = Triangle::new(
+            1,
+            Tuple::point(-3.0, 7.0, 2.0),
+            Tuple::point(6.0, 2.0, -4.0),
+            Tuple::point(2.0, -1.0, -1.0),
+        );
+        let bounds = triangle.bounds();
+        assert_eq!(bounds.min, Tuple::point(-3.0, -1.0, -4.0));
+        assert_eq!(bounds.max, Tuple::point(6.0, 7.0, 2.0));
+    }
+}
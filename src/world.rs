@@ -1,48 +1,92 @@
-use crate::lighting::lighting;
+use crate::background::Background;
+use crate::bvh::Bvh;
+use crate::environment::{environment_lighting, EnvironmentLight};
+use crate::lighting::{lighting, Light, LightSample};
 use crate::ray::schlick;
 use crate::ray::Intersection;
 use crate::ray::{hit, prepare_computations, Computation, Ray};
 use crate::shape::Shape;
 use crate::Color;
-use crate::PointLight;
 use crate::Tuple;
 use crate::BLACK;
 
 pub struct World {
-    pub objects: Vec<Box<dyn Shape>>,
-    pub light: PointLight,
+    // Only changed through add_object and objects_mut, which drop the BVH.
+    objects: Vec<Box<dyn Shape>>,
+    pub lights: Vec<Box<dyn Light>>,
+    // What rays that miss everything see.
+    pub background: Background,
+    // Lights the scene from every direction, in addition to the lights.
+    pub environment: Option<EnvironmentLight>,
+    // Built by build_bvh, after all objects are added. Dropped when they change again.
+    bvh: Option<Bvh>,
 }
 
 impl World {
-    pub fn new(light: PointLight) -> Self {
+    pub fn new(light: impl Light + 'static) -> Self {
         World {
             objects: vec![],
-            light,
+            lights: vec![Box::new(light)],
+            background: Background::Solid(BLACK),
+            environment: None,
+            bvh: None,
         }
     }
+
+    pub fn add_light(&mut self, light: impl Light + 'static) {
+        self.lights.push(Box::new(light));
+    }
+
+    // Drops the BVH, as the objects may be changed in any way.
+    pub fn objects_mut(&mut self) -> &mut Vec<Box<dyn Shape>> {
+        self.bvh = None;
+        &mut self.objects
+    }
+
+    pub fn add_object(&mut self, object: impl Shape + 'static) {
+        self.objects_mut().push(Box::new(object));
+    }
+
+    // Must be called again after the objects have changed, as they are tested one by one
+    // until then.
+    pub fn build_bvh(&mut self) {
+        for object in self.objects.iter_mut() {
+            object.build_bvh();
+        }
+        self.bvh = Some(Bvh::build(&self.objects));
+    }
 }
 
 pub fn intersect_world<'a>(world: &'a World, ray: &Ray) -> Vec<Intersection<'a>> {
-    let mut xs: Vec<_> = world
-        .objects
-        .iter()
-        .flat_map(|obj| (*obj).intersections_by(ray))
-        .collect();
+    let mut xs: Vec<_> = match &world.bvh {
+        Some(bvh) => bvh.intersect(&world.objects, ray, ray),
+        None => world
+            .objects
+            .iter()
+            .flat_map(|obj| (*obj).intersections_by(ray))
+            .collect(),
+    };
     xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
     xs
 }
 
 fn shade_hit(world: &World, computation: &Computation, remaining: u32) -> Color {
-    let is_shadowed = is_shadowed(world, &computation.over_point);
-    let surface = lighting(
-        &computation.object.get_material(),
-        computation.object,
-        &world.light,
-        &computation.over_point,
-        &computation.eye_direction,
-        &computation.surface_normalv,
-        is_shadowed,
-    );
+    let mut surface = computation.object.get_material().emitted();
+    for light in world.lights.iter() {
+        let light_intensity = intensity_at(world, light.as_ref(), &computation.over_point, computation.time);
+        surface = surface + lighting(
+            &computation.object.get_material(),
+            computation.object,
+            light.as_ref(),
+            &computation.over_point,
+            &computation.eye_direction,
+            &computation.surface_normalv,
+            light_intensity,
+        );
+    }
+    if let Some(environment) = &world.environment {
+        surface = surface + environment_lighting(world, environment, computation);
+    }
 
     let reflected = reflected_color(&world, &computation, remaining);
     let refracted = refracted_color(&world, &computation, remaining);
@@ -67,7 +111,7 @@ pub fn color_at(world: &World, ray: &Ray, remaining: u32) -> Color {
             let comp = prepare_computations(h, ray, &intersections);
             color = shade_hit(world, &comp, remaining);
         }
-        None => color = Color::new(0.0, 0.0, 0.0),
+        None => color = world.background.color_at(&ray.direction),
     }
     //println!("Color {}, remaining: {}", color, remaining);
     color
@@ -78,25 +122,41 @@ pub fn reflected_color<'a>(world: &'a World, comps: &Computation, remaining: u32
     if remaining <= 0 || comps.object.get_material().reflective == 0.0 {
         return BLACK;
     }
-    let reflected_ray = Ray::new(comps.over_point, comps.reflectv);
+    let reflected_ray = Ray::new_at_time(comps.over_point, comps.reflectv, comps.time);
     let color = color_at(world, &reflected_ray, remaining - 1);
 
     color * reflective
 }
 
-pub fn is_shadowed<'a>(world: &'a World, point: &Tuple) -> bool {
-    let vector = world.light.position - *point;
-    let distance = vector.mag();
-    let direction = vector.normalize();
-
-    let ray = Ray::new(*point, direction);
-    let intersections = intersect_world(world, &ray);
-    let hit = hit(&intersections);
+// True if anything is hit in [0, max_distance). Unlike intersect_world, it neither
+// collects nor sorts the intersections, and stops at the first one it finds.
+pub fn is_occluded(world: &World, ray: &Ray, max_distance: f32) -> bool {
+    match &world.bvh {
+        Some(bvh) => bvh.occludes(&world.objects, ray, ray, max_distance),
+        None => world
+            .objects
+            .iter()
+            .any(|obj| obj.occludes(ray, max_distance)),
+    }
+}
 
-    match hit {
-        Some(h) => return h.t < distance,
-        None => return false,
+// The fraction of the light's samples that are visible from point, at the given time.
+pub fn intensity_at(world: &World, light: &dyn Light, point: &Tuple, time: f32) -> f32 {
+    // No need to look for shadows where the light doesn't reach anyway.
+    if light.falloff(point) <= 0.0 {
+        return 0.0;
     }
+    let samples = light.samples(point);
+    let visible = samples
+        .iter()
+        .filter(|sample| !is_shadowed(world, sample, point, time))
+        .count();
+    visible as f32 / samples.len() as f32
+}
+
+pub fn is_shadowed(world: &World, sample: &LightSample, point: &Tuple, time: f32) -> bool {
+    let ray = Ray::new_at_time(*point, sample.direction, time);
+    is_occluded(world, &ray, sample.distance)
 }
 
 pub fn refracted_color<'a>(world: &'a World, comps: &Computation, remaining: u32) -> Color {
@@ -118,7 +178,7 @@ pub fn refracted_color<'a>(world: &'a World, comps: &Computation, remaining: u32
 
     let direction =
         comps.surface_normalv * (n_ratio * cos_i - cos_t) - comps.eye_direction * n_ratio;
-    let refract_ray = Ray::new(comps.under_point, direction);
+    let refract_ray = Ray::new_at_time(comps.under_point, direction, comps.time);
 
     let color =
         color_at(world, &refract_ray, remaining - 1) * comps.object.get_material().transparency;
@@ -128,16 +188,19 @@ pub fn refracted_color<'a>(world: &'a World, comps: &Computation, remaining: u32
 #[cfg(test)]
 mod tests {
 
+    use crate::background::Background;
     use crate::ray::{Intersection, Ray};
-    use crate::world::{intersect_world, prepare_computations, shade_hit, color_at, is_shadowed, reflected_color, refracted_color, World};    
+    use crate::world::{intersect_world, prepare_computations, shade_hit, color_at, intensity_at, is_occluded, is_shadowed, reflected_color, refracted_color, World};    
     use crate::sphere::Sphere;
     use crate::Tuple;
     use crate::Shape;
-    use crate::lighting::{PointLight};
+    use crate::lighting::{AreaLight, Attenuation, DirectionalLight, LightSample, PointLight, SpotLight};
     use crate::Matrix4;
-    use crate::color::{BLACK, Color};
+    use crate::color::{BLACK, WHITE, Color};
+    use crate::material::Material;
     use crate::plane::Plane;
     use crate::pattern::TestPattern;
+    use crate::math::PI;
 
     impl World {
         pub fn default() -> Self {
@@ -154,9 +217,33 @@ mod tests {
 
             World {
                 objects: vec![Box::new(s1), Box::new(s2)],
-                light,
+                lights: vec![Box::new(light)],
+                background: Background::Solid(BLACK),
+                environment: None,
+                bvh: None,
             }
         }
+
+        // Nothing but a white sphere, which only gives off light, so pixels are white where
+        // they see it and black elsewhere.
+        pub fn glowing_sphere(transformation: Matrix4) -> Self {
+            let mut world = World::new(PointLight::new(Tuple::point(0.0, 0.0, -10.0), BLACK));
+            let mut sphere = Sphere::new(1);
+            sphere.set_transformation(transformation);
+            sphere.material = Material::emissive(WHITE);
+            world.objects.push(Box::new(sphere));
+            world
+        }
+
+        // A glowing sphere covering the left of the image of Camera::new(10, 10, PI / 2).
+        // Its edge runs through the middle of pixel column 4.
+        pub fn half_covered() -> Self {
+            World::glowing_sphere(Matrix4::identity().translate(100.4988, 0.0, 0.0).scale(100.0, 100.0, 100.0))
+        }
+    }
+
+    fn towards_light(point: &Tuple) -> LightSample {
+        LightSample::towards(point, &Tuple::point(-10.0, 10.0, -10.0))
     }
 
     #[test]
@@ -174,10 +261,7 @@ mod tests {
     fn shading_an_interection() {
         let world: World = World::default();
         let ray = Ray::default();
-        let i = Intersection {
-            obj: world.objects[0].as_ref(),
-            t: 4.0,
-        };
+        let i = Intersection::new(world.objects[0].as_ref(), 4.0);
 
         let comps = prepare_computations(&i, &ray, &vec![]);
         let color = shade_hit(&world, &comps, 0);
@@ -186,13 +270,10 @@ mod tests {
     #[test]
     fn shading_an_interection_from_the_inside() {
         let mut world: World = World::default();
-        world.light.position = Tuple::point(0.0, 0.25, 0.0);
+        world.lights = vec![Box::new(PointLight::new(Tuple::point(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0)))];
         let mut ray = Ray::default();
         ray.origin = Tuple::point(0.0, 0.0, 0.0);
-        let i = Intersection {
-            obj: world.objects[1].as_ref(),
-            t: 0.5,
-        };
+        let i = Intersection::new(world.objects[1].as_ref(), 0.5);
 
         let comps = prepare_computations(&i, &ray, &vec![]);
         let color = shade_hit(&world, &comps, 0);
@@ -207,6 +288,27 @@ mod tests {
         assert_eq!(color, Color::new(0.0, 0.0, 0.0));
     }
     #[test]
+    fn the_color_when_a_ray_misses_is_the_background() {
+        let mut world: World = World::default();
+        world.background = Background::Gradient { bottom: BLACK, top: Color::new(0.2, 0.4, 1.0) };
+        let mut ray = Ray::default();
+        ray.direction = Tuple::vector(0.0, 1.0, 0.0);
+        assert_eq!(color_at(&world, &ray, 0), Color::new(0.2, 0.4, 1.0));
+    }
+    #[test]
+    fn a_reflective_surface_reflects_the_background() {
+        let mut world = World::new(PointLight::new(Tuple::point(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)));
+        world.background = Background::Solid(Color::new(0.2, 0.4, 1.0));
+        let mut mirror = Plane::new(1);
+        mirror.material.ambient = 0.0;
+        mirror.material.diffuse = 0.0;
+        mirror.material.specular = 0.0;
+        mirror.material.reflective = 0.5;
+        world.objects.push(Box::new(mirror));
+        let ray = Ray::new(Tuple::point(0.0, 1.0, -1.0), Tuple::vector(0.0, -1.0, 1.0).normalize());
+        assert_eq!(color_at(&world, &ray, 1), Color::new(0.1, 0.2, 0.5));
+    }
+    #[test]
     fn the_color_when_a_ray_hits() {
         let world: World = World::default();
         let ray = Ray::default();
@@ -217,25 +319,141 @@ mod tests {
     fn there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
         let world: World = World::default();
         let point = Tuple::point(0.0, 10.0, 0.0);
-        assert_eq!(is_shadowed(&world, &point), false);
+        assert_eq!(is_shadowed(&world, &towards_light(&point), &point, 0.0), false);
     }
     #[test]
     fn the_shadow_when_an_object_is_between_the_point_and_the_light() {
         let world: World = World::default();
         let point = Tuple::point(10.0, -10.0, 10.0);
-        assert_eq!(is_shadowed(&world, &point), true);
+        assert_eq!(is_shadowed(&world, &towards_light(&point), &point, 0.0), true);
     }
     #[test]
     fn there_is_no_shadow_when_an_object_is_behind_the_light() {
         let world: World = World::default();
         let point = Tuple::point(-20.0, 20.0, -20.0);
-        assert_eq!(is_shadowed(&world, &point), false);
+        assert_eq!(is_shadowed(&world, &towards_light(&point), &point, 0.0), false);
     }
     #[test]
     fn there_is_no_shadow_when_an_object_is_behind_the_point() {
         let world: World = World::default();
         let point = Tuple::point(-2.0, 2.0, -2.0);
-        assert_eq!(is_shadowed(&world, &point), false);
+        assert_eq!(is_shadowed(&world, &towards_light(&point), &point, 0.0), false);
+    }
+    #[test]
+    fn a_point_light_is_either_fully_visible_or_hidden() {
+        let world: World = World::default();
+        let test_cases = vec![
+            (Tuple::point(0.0, 1.0001, 0.0), 1.0),
+            (Tuple::point(-1.0001, 0.0, 0.0), 1.0),
+            (Tuple::point(0.0, 0.0, -1.0001), 1.0),
+            (Tuple::point(0.0, 0.0, 1.0001), 0.0),
+            (Tuple::point(1.0001, 0.0, 0.0), 0.0),
+            (Tuple::point(0.0, -1.0001, 0.0), 0.0),
+            (Tuple::point(0.0, 0.0, 0.0), 0.0),
+        ];
+        for test_case in test_cases {
+            assert_eq!(intensity_at(&world, world.lights[0].as_ref(), &test_case.0, 0.0), test_case.1, "Point: {}", test_case.0);
+        }
+    }
+    #[test]
+    fn an_area_light_is_partially_visible_in_the_penumbra() {
+        let world: World = World::default();
+        let light = AreaLight {
+            jitter: false,
+            ..AreaLight::new(
+                Tuple::point(-0.5, -0.5, -5.0),
+                Tuple::vector(1.0, 0.0, 0.0),
+                2,
+                Tuple::vector(0.0, 1.0, 0.0),
+                2,
+                Color::new(1.0, 1.0, 1.0),
+            )
+        };
+        let test_cases = vec![
+            (Tuple::point(0.0, 0.0, 2.0), 0.0),
+            (Tuple::point(1.0, -1.0, 2.0), 0.25),
+            (Tuple::point(1.5, 0.0, 2.0), 0.5),
+            (Tuple::point(1.25, 1.25, 3.0), 0.75),
+            (Tuple::point(0.0, 0.0, -2.0), 1.0),
+        ];
+        for test_case in test_cases {
+            assert_eq!(intensity_at(&world, &light, &test_case.0, 0.0), test_case.1, "Point: {}", test_case.0);
+        }
+    }
+    #[test]
+    fn a_spot_light_casts_shadows_only_within_its_cone() {
+        let world: World = World::default();
+        let light = SpotLight::new(
+            Tuple::point(0.0, 10.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        assert_eq!(intensity_at(&world, &light, &Tuple::point(0.0, 1.0001, 0.0), 0.0), 1.0);
+        assert_eq!(intensity_at(&world, &light, &Tuple::point(0.0, -1.0001, 0.0), 0.0), 0.0);
+        assert_eq!(intensity_at(&world, &light, &Tuple::point(0.0, 1.0001, 20.0), 0.0), 0.0);
+    }
+    #[test]
+    fn shadows_of_a_directional_light_reach_infinitely_far() {
+        let world: World = World::default();
+        let light = DirectionalLight::new(Tuple::vector(0.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(intensity_at(&world, &light, &Tuple::point(0.0, -1000.0, 0.0), 0.0), 0.0);
+        assert_eq!(intensity_at(&world, &light, &Tuple::point(0.0, 1.0001, 0.0), 0.0), 1.0);
+        assert_eq!(intensity_at(&world, &light, &Tuple::point(2.0, -1000.0, 0.0), 0.0), 1.0);
+    }
+    #[test]
+    fn nothing_is_lit_beyond_the_range_of_a_light() {
+        let mut world: World = World::default();
+        let mut light = PointLight::new(Tuple::point(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.attenuation = Attenuation::InverseSquare { range: 20.0 };
+        assert!(intensity_at(&world, &light, &Tuple::point(0.0, 1.0001, 0.0), 0.0) > 0.0);
+        assert_eq!(intensity_at(&world, &light, &Tuple::point(0.0, 1.0001, 40.0), 0.0), 0.0);
+
+        world.lights = vec![Box::new(light)];
+        let ray = Ray::new(Tuple::point(0.0, 5.0, 40.0), Tuple::vector(0.0, -1.0, 0.0));
+        let mut floor = Plane::new(3);
+        floor.material.ambient = 0.0;
+        world.objects.push(Box::new(floor));
+        assert_eq!(color_at(&world, &ray, 0), BLACK);
+    }
+    #[test]
+    fn shadows_are_the_same_with_a_bvh() {
+        let mut world: World = World::default();
+        world.build_bvh();
+        assert_eq!(is_shadowed(&world, &towards_light(&Tuple::point(0.0, 10.0, 0.0)), &Tuple::point(0.0, 10.0, 0.0), 0.0), false);
+        assert_eq!(is_shadowed(&world, &towards_light(&Tuple::point(10.0, -10.0, 10.0)), &Tuple::point(10.0, -10.0, 10.0), 0.0), true);
+        assert_eq!(is_shadowed(&world, &towards_light(&Tuple::point(-20.0, 20.0, -20.0)), &Tuple::point(-20.0, 20.0, -20.0), 0.0), false);
+        assert_eq!(is_shadowed(&world, &towards_light(&Tuple::point(-2.0, 2.0, -2.0)), &Tuple::point(-2.0, 2.0, -2.0), 0.0), false);
+    }
+    #[test]
+    fn objects_added_after_building_the_bvh_are_still_found() {
+        let mut world: World = World::default();
+        world.build_bvh();
+        let mut s3 = Sphere::new(3);
+        s3.set_transformation(Matrix4::identity().translate(0.0, 0.0, 10.0));
+        world.add_object(s3);
+        assert!(world.bvh.is_none());
+        let ray = Ray::new(Tuple::point(0.0, 0.0, 20.0), Tuple::vector(0.0, 0.0, -1.0));
+        let xs = intersect_world(&world, &ray);
+        assert_eq!(xs.len(), 6);
+        assert_eq!(xs[0].obj.get_id(), 3);
+        assert!(is_occluded(&world, &ray, 10.0));
+    }
+    #[test]
+    fn objects_moved_after_building_the_bvh_are_found_where_they_are() {
+        let mut world: World = World::default();
+        world.build_bvh();
+        world.objects_mut()[1].set_transformation(Matrix4::identity().translate(0.0, 0.0, 10.0));
+        assert!(world.bvh.is_none());
+        let ray = Ray::new(Tuple::point(0.0, 0.0, 20.0), Tuple::vector(0.0, 0.0, -1.0));
+        let xs = intersect_world(&world, &ray);
+        assert_eq!(xs[0].obj.get_id(), 2);
+        assert_eq!(xs[0].t, 9.0);
+        assert!(is_occluded(&world, &ray, 10.0));
+
+        world.build_bvh();
+        assert_eq!(intersect_world(&world, &ray)[0].t, 9.0);
     }
     #[test]
     fn shade_hit_is_given_an_intersection_in_shadow() {
@@ -252,10 +470,7 @@ mod tests {
         w.objects.push(s1);
         w.objects.push(s2);
         let ray = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
-        let i = Intersection {
-            obj: w.objects[1].as_ref(),
-            t: 4.0
-        };
+        let i = Intersection::new(w.objects[1].as_ref(), 4.0);
         let comps = prepare_computations(&i, &ray, &vec!());
         let color = shade_hit(&w, &comps, 0);
         assert_eq!(color, Color::new(0.1, 0.1, 0.1));
@@ -265,10 +480,7 @@ mod tests {
         let mut world: World = World::default();
         world.objects[1].as_mut().get_mut_material().ambient = 1.0;
         let ray = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
-        let intersection =  Intersection {
-            obj: world.objects[1].as_ref(),
-            t: 1.0
-        };
+        let intersection =  Intersection::new(world.objects[1].as_ref(), 1.0);
         let comps = prepare_computations(&intersection, &ray, &vec!());
         let color = reflected_color(&world, &comps, 1);
         assert_eq!(color, BLACK);
@@ -282,10 +494,7 @@ mod tests {
         plane.set_transformation(Matrix4::identity().translate(0.0, -1.0, 0.0));
         world.objects.push(Box::new(plane));
         let ray = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -(2.0_f32.sqrt()/2f32), 2.0_f32.sqrt()/2f32));
-        let intersection =  Intersection {
-            obj: world.objects[2].as_ref(),
-            t: 2.0_f32.sqrt()
-        };
+        let intersection =  Intersection::new(world.objects[2].as_ref(), 2.0_f32.sqrt());
         let comps = prepare_computations(&intersection, &ray, &vec!());
         let color = reflected_color(&world, &comps, 1);
         assert_eq!(color, Color::new(0.19050309, 0.23812884, 0.14287731));
@@ -299,14 +508,59 @@ mod tests {
         plane.set_transformation(Matrix4::identity().translate(0.0, -1.0, 0.0));
         world.objects.push(Box::new(plane));
         let ray = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -(2.0_f32.sqrt()/2f32), 2.0_f32.sqrt()/2f32));
-        let intersection =  Intersection {
-            obj: world.objects[2].as_ref(),
-            t: 2.0_f32.sqrt()
-        };
+        let intersection =  Intersection::new(world.objects[2].as_ref(), 2.0_f32.sqrt());
         let comps = prepare_computations(&intersection, &ray, &vec!());
         let color = shade_hit(&world, &comps, 1);
         assert_eq!(color, Color::new(0.87692857, 0.9245543, 0.8293028));
     }
+    fn floor_lit_by_two_lights() -> World {
+        let red = PointLight::new(Tuple::point(-10.0, 10.0, 0.0), Color::new(1.0, 0.0, 0.0));
+        let green = PointLight::new(Tuple::point(10.0, 10.0, 0.0), Color::new(0.0, 1.0, 0.0));
+        let mut world = World::new(red);
+        world.add_light(green);
+        world.objects.push(Box::new(Plane::new(1)));
+        world
+    }
+    #[test]
+    fn shade_hit_sums_the_contribution_of_every_light() {
+        let world = floor_lit_by_two_lights();
+        let ray = Ray::new(Tuple::point(-2.0, 1.0, -1.0), Tuple::vector(0.0, -1.0, 1.0).normalize());
+        let i = Intersection::new(world.objects[0].as_ref(), 2.0_f32.sqrt());
+        let comps = prepare_computations(&i, &ray, &vec![]);
+        let color = shade_hit(&world, &comps, 0);
+        assert_eq!(color, Color::new(0.80275, 0.67613, 0.0));
+    }
+    #[test]
+    fn shade_hit_tests_the_shadow_of_every_light_on_its_own() {
+        // The sphere is between the point and the green light, but not the red one.
+        let mut world = floor_lit_by_two_lights();
+        let mut sphere = Sphere::new(2);
+        sphere.set_transformation(Matrix4::identity().translate(0.0, 1.0, 0.0));
+        world.objects.push(Box::new(sphere));
+        let ray = Ray::new(Tuple::point(-2.0, 1.0, -1.0), Tuple::vector(0.0, -1.0, 1.0).normalize());
+        let i = Intersection::new(world.objects[0].as_ref(), 2.0_f32.sqrt());
+        let comps = prepare_computations(&i, &ray, &vec![]);
+        let color = shade_hit(&world, &comps, 0);
+        assert_eq!(color, Color::new(0.80275, 0.1, 0.0));
+    }
+    #[test]
+    fn an_emissive_surface_glows_even_in_shadow() {
+        let mut world = World::new(PointLight::new(Tuple::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)));
+        world.objects.push(Box::new(Sphere::new(1)));
+        let mut glowing = Sphere::new(2);
+        glowing.set_transformation(Matrix4::identity().translate(0.0, 0.0, 10.0));
+        glowing.material.emission = Color::new(1.0, 0.2, 0.0);
+        glowing.material.emission_strength = 2.0;
+        world.objects.push(Box::new(glowing));
+
+        let ray = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let i = Intersection::new(world.objects[1].as_ref(), 4.0);
+        let comps = prepare_computations(&i, &ray, &vec![]);
+        assert_eq!(shade_hit(&world, &comps, 0), Color::new(2.1, 0.5, 0.1));
+
+        world.lights = vec![];
+        assert_eq!(shade_hit(&world, &comps, 0), Color::new(2.0, 0.4, 0.0));
+    }
     #[test]
     fn color_at_with_mutually_reflective_surfaces_completes() {
         let light = PointLight::new(Tuple::point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
@@ -332,10 +586,7 @@ mod tests {
         plane.set_transformation(Matrix4::identity().translate(0.0, -1.0, 0.0));
         world.objects.push(Box::new(plane));
         let ray = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -(2_f32.sqrt()/2f32), 2_f32.sqrt()/2f32));
-        let intersection =  Intersection {
-            obj: world.objects[2].as_ref(),
-            t: 2.0_f32.sqrt()
-        };
+        let intersection =  Intersection::new(world.objects[2].as_ref(), 2.0_f32.sqrt());
         let comps = prepare_computations(&intersection, &ray, &vec!());
         let color = reflected_color(&world, &comps, 0);
         assert_eq!(color, BLACK);
@@ -345,8 +596,8 @@ mod tests {
         let world: World = World::default();        
         let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
         let xs : Vec<Intersection> = vec!(
-            Intersection { t: 4.0, obj: world.objects[0].as_ref()},
-            Intersection { t: 6.0, obj: world.objects[0].as_ref()},
+            Intersection::new(world.objects[0].as_ref(), 4.0),
+            Intersection::new(world.objects[0].as_ref(), 6.0),
         );
         let comps = prepare_computations(&xs[0], &ray, &xs);
         assert_eq!(refracted_color(&world, &comps, 5), BLACK);
@@ -356,8 +607,8 @@ mod tests {
         let world: World = World::default();        
         let ray = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
         let xs : Vec<Intersection> = vec!(
-            Intersection { t: 4.0, obj: world.objects[0].as_ref()},
-            Intersection { t: 6.0, obj: world.objects[0].as_ref()},
+            Intersection::new(world.objects[0].as_ref(), 4.0),
+            Intersection::new(world.objects[0].as_ref(), 6.0),
         );
         let comps = prepare_computations(&xs[0], &ray, &xs);
         assert_eq!(refracted_color(&world, &comps, 0), BLACK);
@@ -370,8 +621,8 @@ mod tests {
         
         let ray = Ray::new(Tuple::point(0.0, 0.0, 2_f32.sqrt()/2.0), Tuple::vector(0.0, 1.0, 0.0));
         let xs : Vec<Intersection> = vec!(
-            Intersection { t: -(2_f32.sqrt()/2.0), obj: world.objects[0].as_ref()},
-            Intersection { t: (2_f32.sqrt()/2.0), obj: world.objects[0].as_ref()},
+            Intersection::new(world.objects[0].as_ref(), -(2_f32.sqrt()/2.0)),
+            Intersection::new(world.objects[0].as_ref(), 2_f32.sqrt()/2.0),
         );
         let comps = prepare_computations(&xs[1], &ray, &xs);
         assert_eq!(refracted_color(&world, &comps, 5), BLACK);
@@ -386,10 +637,10 @@ mod tests {
         
         let ray = Ray::new(Tuple::point(0.0, 0.0, 0.1), Tuple::vector(0.0, 1.0, 0.0));
         let xs : Vec<Intersection> = vec!(
-            Intersection { t: -0.9899, obj: world.objects[0].as_ref()},
-            Intersection { t: -0.4899, obj: world.objects[1].as_ref()},
-            Intersection { t:  0.4899, obj: world.objects[1].as_ref()},
-            Intersection { t: 0.9899, obj: world.objects[0].as_ref()},
+            Intersection::new(world.objects[0].as_ref(), -0.9899),
+            Intersection::new(world.objects[1].as_ref(), -0.4899),
+            Intersection::new(world.objects[1].as_ref(), 0.4899),
+            Intersection::new(world.objects[0].as_ref(), 0.9899),
         );
         let comps = prepare_computations(&xs[2], &ray, &xs);
         assert_eq!(refracted_color(&world, &comps, 5), Color::new(0.0, 0.9978715, 0.047472));
@@ -409,7 +660,7 @@ mod tests {
         world.objects.push(Box::new(ball));
         let ray = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -(2_f32.sqrt()/2.0), 2_f32.sqrt()/2.0));
         let xs : Vec<Intersection> = vec!(
-            Intersection { t: 2_f32.sqrt(), obj: world.objects[2].as_ref()},
+            Intersection::new(world.objects[2].as_ref(), 2_f32.sqrt()),
         );
         let comps = prepare_computations(&xs[0], &ray, &xs);
         let color = shade_hit(&world, &comps, 5);
@@ -431,7 +682,7 @@ mod tests {
         world.objects.push(Box::new(ball));
         let ray = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -(2_f32.sqrt()/2.0), 2_f32.sqrt()/2.0));
         let xs : Vec<Intersection> = vec!(
-            Intersection { t: 2_f32.sqrt(), obj: world.objects[2].as_ref()},
+            Intersection::new(world.objects[2].as_ref(), 2_f32.sqrt()),
         );
         let comps = prepare_computations(&xs[0], &ray, &xs);
         let color = shade_hit(&world, &comps, 5);
@@ -1,6 +1,6 @@
-use crate::color::{Color, BLACK, WHITE};
+use crate::color::Color;
 use crate::cube::Cube;
-use crate::lighting::PointLight;
+use crate::lighting::{DirectionalLight, PointLight};
 use crate::matrix::Matrix4;
 use crate::pattern::CheckerPattern;
 use crate::pattern::GradientPattern;
@@ -130,16 +130,16 @@ pub fn generate_world() -> World {
     left.material.pattern = Some(Box::new(left_pattern)); */
 
     // REST
-    let light = PointLight::new(
-        Tuple::point(0_f32, 200_f32, -100_f32),
+    let light = DirectionalLight::new(
+        Tuple::vector(0_f32, -200_f32, 100_f32),
         Color::new(1.0, 1.0, 1.0),
     );
     let mut world = World::new(light);
-    world.objects.push(Box::new(wall));
-    world.objects.push(Box::new(floor));
-    world.objects.push(Box::new(middle));
-    world.objects.push(Box::new(left));
-    world.objects.push(Box::new(right));
+    world.add_object(wall);
+    world.add_object(floor);
+    world.add_object(middle);
+    world.add_object(left);
+    world.add_object(right);
     return world;
 }
 
@@ -191,9 +191,9 @@ pub fn generate_test_world() -> World {
         Color::new(1.0, 1.0, 1.0),
     );
     let mut world = World::new(light);
-    world.objects.push(Box::new(wall));
-    world.objects.push(Box::new(floor));
-    world.objects.push(Box::new(left));
+    world.add_object(wall);
+    world.add_object(floor);
+    world.add_object(left);
 
     return world;
 }